@@ -0,0 +1,251 @@
+//! Element that distorts the stream of acks on its way back to the sender
+use crate::base::{Coord, Element, Packet, ACK_PKT_WIDTH};
+use crate::check;
+use crate::scene::Shape;
+use crate::theme::Theme;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// Gap between acks waiting in the queue
+const HELD_SPACING: f64 = ACK_PKT_WIDTH * 1.5;
+
+/// What an `AckPath` does to acks
+#[derive(Clone, Debug, Default)]
+pub enum AckChannel {
+    /// Acks pass through untouched
+    #[default]
+    Plain,
+    /// Hold acks and release them in batches every `interval` ticks, like a
+    /// link that only gets a transmission opportunity every so often. A batch
+    /// leaves back to back, one ack per tick
+    Aggregate { interval: u64 },
+    /// Forward only every `keep_every`-th ack and absorb the rest, as
+    /// receivers and middleboxes that thin cumulative acks do. Forwarded acks
+    /// also cover the packets whose acks were absorbed
+    Thin { keep_every: u64 },
+    /// Release every ack in order, but `factor` times closer together than
+    /// they arrived. A burst starts when an ack arrives to find nothing held;
+    /// its first ack is held for `hold` ticks. Acks that would be released
+    /// before they arrive leave right away
+    Compress { hold: u64, factor: f64 },
+}
+
+/// Sits on the return path and models how real networks distort acks
+pub struct AckPath<N: Element + ?Sized> {
+    /// Where acks arrive. Held acks pile up to the left of this, the way acks
+    /// travel, so they stay clear of whatever feeds us
+    coord: Coord,
+    channel: AckChannel,
+    held: VecDeque<Packet>,
+    /// Tick at which to release each ack in `held` (Compress)
+    release_at: VecDeque<f64>,
+    /// Number of acks at the front of `held` that are being released
+    /// (Aggregate)
+    num_releasing: usize,
+    /// When the current burst started (Compress)
+    burst_start: u64,
+    /// Number of acks seen so far and the number of packets covered by those
+    /// absorbed since the last one we forwarded (Thin)
+    num_seen: u64,
+    num_absorbed: u64,
+    next: Rc<RefCell<N>>,
+    tick_num: u64,
+}
+
+impl<N: Element + ?Sized> AckPath<N> {
+    /// Panics if `channel` would hold acks forever or divide by 0
+    pub fn new(coord: Coord, channel: AckChannel, next: Rc<RefCell<N>>) -> Self {
+        match channel {
+            AckChannel::Plain => {}
+            AckChannel::Aggregate { interval } => {
+                assert!(interval > 0, "Aggregate interval must be positive");
+            }
+            AckChannel::Thin { keep_every } => {
+                assert!(keep_every > 0, "Thin keep_every must be positive");
+            }
+            AckChannel::Compress { factor, .. } => {
+                // Also rejects NaN
+                assert!(
+                    factor > 0. && factor.is_finite(),
+                    "Compress factor must be positive, not {}",
+                    factor
+                );
+            }
+        }
+        Self {
+            coord,
+            channel,
+            held: VecDeque::new(),
+            release_at: VecDeque::new(),
+            num_releasing: 0,
+            burst_start: 0,
+            num_seen: 0,
+            num_absorbed: 0,
+            next,
+            tick_num: 0,
+        }
+    }
+
+    /// Add `pkt` to the back of the queue
+    fn hold(&mut self, pkt: &Packet) {
+        for other in &mut self.held {
+            other.coord.0 -= HELD_SPACING;
+        }
+        let mut pkt = *pkt;
+        pkt.coord = self.coord;
+        self.held.push_back(pkt);
+    }
+
+    /// Send the ack at the front of the queue on
+    fn release(&mut self) {
+        let pkt = self.held.pop_front().unwrap();
+        self.next.borrow_mut().enqueue(&pkt);
+    }
+
+    fn forward(&mut self, pkt: &Packet) {
+        let mut pkt = *pkt;
+        pkt.coord = self.coord;
+        self.next.borrow_mut().enqueue(&pkt);
+    }
+}
+
+impl<N: Element + ?Sized> Element for AckPath<N> {
+    fn get_enqueue_coord(&self) -> Coord {
+        self.coord
+    }
+
+    fn enqueue(&mut self, pkt: &Packet) {
+        match self.channel {
+            AckChannel::Plain => self.forward(pkt),
+            AckChannel::Aggregate { .. } => self.hold(pkt),
+            AckChannel::Thin { keep_every } => {
+                self.num_seen += 1;
+                if self.num_seen.is_multiple_of(keep_every) {
+                    let mut pkt = *pkt;
+                    pkt.covers += self.num_absorbed;
+                    self.num_absorbed = 0;
+                    self.forward(&pkt);
+                } else {
+                    self.num_absorbed += pkt.covers;
+                    check::dropped(pkt);
+                }
+            }
+            AckChannel::Compress { hold, factor } => {
+                if self.held.is_empty() {
+                    self.burst_start = self.tick_num;
+                }
+                let since_start = (self.tick_num - self.burst_start) as f64;
+                self.release_at
+                    .push_back((self.burst_start + hold) as f64 + since_start / factor);
+                self.hold(pkt);
+            }
+        }
+    }
+
+    fn get_pkts(&self) -> Vec<Packet> {
+        self.held.iter().copied().collect()
+    }
+
+    fn tick(&mut self) {
+        self.tick_num += 1;
+        match self.channel {
+            AckChannel::Plain | AckChannel::Thin { .. } => {}
+            AckChannel::Aggregate { interval } => {
+                if self.tick_num.is_multiple_of(interval) {
+                    self.num_releasing = self.held.len();
+                }
+                if self.num_releasing > 0 {
+                    self.release();
+                    self.num_releasing -= 1;
+                }
+            }
+            AckChannel::Compress { .. } => {
+                while self
+                    .release_at
+                    .front()
+                    .is_some_and(|t| *t <= self.tick_num as f64)
+                {
+                    self.release_at.pop_front();
+                    self.release();
+                }
+            }
+        }
+    }
+
+    fn draw(&self, theme: &Theme) -> Vec<Shape> {
+        self.held.iter().flat_map(|pkt| pkt.draw(theme)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::{AckedFlow, AckedFlowSim};
+    use crate::sender::EcnResponse;
+    use crate::simple_elems::Sink;
+
+    fn ack_path(channel: AckChannel) -> AckPath<Sink> {
+        let sink = Rc::new(RefCell::new(Sink {
+            coord: Coord(0., 0.),
+        }));
+        AckPath::new(Coord(0., 0.), channel, sink)
+    }
+
+    #[test]
+    #[should_panic(expected = "Compress factor must be positive")]
+    fn rejects_zero_compression() {
+        ack_path(AckChannel::Compress {
+            hold: 4,
+            factor: 0.,
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Compress factor must be positive")]
+    fn rejects_nan_compression() {
+        ack_path(AckChannel::Compress {
+            hold: 4,
+            factor: f64::NAN,
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Aggregate interval must be positive")]
+    fn rejects_zero_interval() {
+        ack_path(AckChannel::Aggregate { interval: 0 });
+    }
+
+    #[test]
+    #[should_panic(expected = "Thin keep_every must be positive")]
+    fn rejects_zero_keep_every() {
+        ack_path(AckChannel::Thin { keep_every: 0 });
+    }
+
+    #[test]
+    fn thinned_acks_open_the_window() {
+        let AckedFlowSim {
+            mut sim, sender, ..
+        } = AckedFlow {
+            delays: (8, 4, 8),
+            bufsize: 64,
+            bottleneck_intersend: 8,
+            response: EcnResponse::Ignore,
+            init_cwnd: 4.,
+            ack_channel: Some(AckChannel::Thin { keep_every: 3 }),
+        }
+        .build();
+
+        // Nothing is dropped, so the window only ever grows
+        let mut cwnd = sender.borrow().cwnd();
+        for _ in 0..2000 {
+            sim.tick();
+            let sender = sender.borrow();
+            assert!(sender.cwnd() >= cwnd);
+            cwnd = sender.cwnd();
+        }
+        let sender = sender.borrow();
+        assert!(sender.cwnd() > 16.);
+        assert!(sender.in_flight() as f64 <= sender.cwnd().ceil());
+    }
+}
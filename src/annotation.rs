@@ -0,0 +1,190 @@
+//! Text drawn on top of the animation: labels, flow legends, a clock and
+//! timed captions
+use crate::base::{Coord, DATA_PKT_WIDTH, PKT_HEIGHT};
+use crate::scene::{Align, Shape, Style};
+use crate::theme::Theme;
+
+/// Font size (in pixels) used for all annotations
+pub(crate) const FONT_SIZE: f64 = 14.;
+
+/// Static text at a fixed location
+pub struct Label {
+    pub text: String,
+    /// Center of the text
+    pub coord: Coord,
+}
+
+/// Text that is shown only while `start <= tick < end`
+#[derive(Clone)]
+pub struct Caption {
+    pub text: String,
+    pub start: u64,
+    pub end: u64,
+}
+
+/// Collection of everything we draw on a frame apart from the elements
+/// themselves
+pub struct Annotations {
+    labels: Vec<Label>,
+    /// Each flow's name and how its packets are drawn
+    legend: Vec<(String, Style)>,
+    /// Top-left corner of the legend
+    legend_coord: Coord,
+    /// Where to draw the tick counter, if at all
+    clock_coord: Option<Coord>,
+    captions: Vec<Caption>,
+    /// Line each caption is drawn on, counting down from `caption_coord`
+    caption_rows: Vec<usize>,
+    /// Center of the first line of captions
+    caption_coord: Coord,
+}
+
+impl Annotations {
+    pub fn new() -> Self {
+        Self {
+            labels: Vec::new(),
+            legend: Vec::new(),
+            legend_coord: Coord(0., 0.),
+            clock_coord: None,
+            captions: Vec::new(),
+            caption_rows: Vec::new(),
+            caption_coord: Coord(0., 0.),
+        }
+    }
+
+    /// Label placed at `coord`
+    pub fn label_at(&mut self, text: &str, coord: Coord) {
+        self.labels.push(Label {
+            text: String::from(text),
+            coord,
+        });
+    }
+
+    /// Label placed at `offset` from `anchor`, e.g. the point where an
+    /// element accepts packets
+    pub fn label(&mut self, text: &str, anchor: Coord, offset: Coord) {
+        self.label_at(text, anchor.add(offset));
+    }
+
+    /// Legend mapping flow names to the style their packets are drawn in. Entry
+    /// `i` describes flow `i`
+    pub fn legend(&mut self, coord: Coord, entries: Vec<(String, Style)>) {
+        self.legend_coord = coord;
+        self.legend = entries;
+    }
+
+    /// Show the current tick at `coord`
+    pub fn clock(&mut self, coord: Coord) {
+        self.clock_coord = Some(coord);
+    }
+
+    /// Show each caption at `coord` between its start and end ticks.
+    /// Captions shown at the same time go on separate lines below it, and
+    /// each keeps its line while it is shown
+    pub fn captions(&mut self, coord: Coord, captions: Vec<Caption>) {
+        self.caption_coord = coord;
+        self.caption_rows = Vec::new();
+        for (i, caption) in captions.iter().enumerate() {
+            let taken: Vec<usize> = captions[..i]
+                .iter()
+                .zip(&self.caption_rows)
+                .filter(|(other, _)| other.start < caption.end && caption.start < other.end)
+                .map(|(_, row)| *row)
+                .collect();
+            let row = (0..).find(|row| !taken.contains(row)).unwrap();
+            self.caption_rows.push(row);
+        }
+        self.captions = captions;
+    }
+
+    pub fn draw(&self, tick: u64, theme: &Theme) -> Vec<Shape> {
+        let mut res = Vec::new();
+        for label in &self.labels {
+            res.push(theme.text(label.text.clone(), label.coord, Align::Center));
+        }
+
+        for (i, (name, style)) in self.legend.iter().enumerate() {
+            let y = self.legend_coord.1 - i as f64 * PKT_HEIGHT * 1.5;
+            let x = self.legend_coord.0;
+            res.push(Shape::Rect {
+                corners: (
+                    Coord(x, y - PKT_HEIGHT / 2.),
+                    Coord(x + DATA_PKT_WIDTH, y + PKT_HEIGHT / 2.),
+                ),
+                style: theme.flow_style(i as u16, *style),
+            });
+            res.push(theme.text(name.clone(), Coord(x + DATA_PKT_WIDTH * 2., y), Align::Left));
+        }
+
+        if let Some(coord) = self.clock_coord {
+            res.push(theme.text(format!("t = {}", tick), coord, Align::Left));
+        }
+
+        for (caption, row) in self.captions.iter().zip(&self.caption_rows) {
+            if caption.start <= tick && tick < caption.end {
+                let coord = self
+                    .caption_coord
+                    .sub(Coord(0., *row as f64 * PKT_HEIGHT * 1.5));
+                res.push(theme.text(caption.text.clone(), coord, Align::Center));
+            }
+        }
+        res
+    }
+}
+
+impl Default for Annotations {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn caption(text: &str, start: u64, end: u64) -> Caption {
+        Caption {
+            text: String::from(text),
+            start,
+            end,
+        }
+    }
+
+    /// Each caption drawn at `tick` and the height it is drawn at
+    fn captions_at(annotations: &Annotations, tick: u64) -> Vec<(String, f64)> {
+        annotations
+            .draw(tick, &Theme::default())
+            .into_iter()
+            .filter_map(|shape| match shape {
+                Shape::Text { text, coord, .. } => Some((text, coord.1)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn concurrent_captions_stack() {
+        let mut annotations = Annotations::new();
+        annotations.captions(
+            Coord(0., 4.),
+            vec![
+                caption("a", 0, 100),
+                caption("b", 50, 150),
+                caption("c", 100, 200),
+                caption("d", 200, 300),
+            ],
+        );
+        let below = 4. - PKT_HEIGHT * 1.5;
+        assert_eq!(captions_at(&annotations, 10), vec![("a".into(), 4.)]);
+        assert_eq!(
+            captions_at(&annotations, 60),
+            vec![("a".into(), 4.), ("b".into(), below)]
+        );
+        // "b" stays put when "a" ends, and "c" takes the free line
+        assert_eq!(
+            captions_at(&annotations, 120),
+            vec![("b".into(), below), ("c".into(), 4.)]
+        );
+        assert_eq!(captions_at(&annotations, 250), vec![("d".into(), 4.)]);
+    }
+}
@@ -1,4 +1,5 @@
-use plotters::prelude::*;
+use crate::scene::{Shape, Style};
+use crate::theme::{AckStyle, Theme};
 
 pub const PKT_HEIGHT: f64 = 1.5;
 pub const DATA_PKT_WIDTH: f64 = 0.25;
@@ -19,6 +20,15 @@ impl Coord {
     pub fn div(&self, fact: f64) -> Coord {
         Coord(self.0 / fact, self.1 / fact)
     }
+
+    pub fn mul(&self, fact: f64) -> Coord {
+        Coord(self.0 * fact, self.1 * fact)
+    }
+
+    /// Euclidean length
+    pub fn norm(&self) -> f64 {
+        self.0.hypot(self.1)
+    }
 }
 
 impl From<Coord> for (f64, f64) {
@@ -35,30 +45,94 @@ pub struct Packet {
     pub coord: Coord,
     /// Address which we will use to route packets
     pub addr: u16,
+    /// Flow the packet belongs to. Routers hash this to pick between
+    /// equal-cost paths
+    pub flow: u16,
     /// How to draw the packet? Color, fill, border etc.
-    pub style: ShapeStyle,
+    pub style: Style,
+    /// Congestion Experienced. Set by an ECN-capable bottleneck on data
+    /// packets and echoed back by the receiver on acks. Marked packets are
+    /// drawn with a cross through them
+    pub ce: bool,
+    /// Set by the receiver on the ack it sends back
+    pub ack: bool,
+    /// Identifies the packet to `check` when checking is on. Acks keep the id
+    /// of the packet they acknowledge. 0 means untracked
+    pub id: u64,
+    /// Set by senders that match acks to the packets they sent. Acks keep the
+    /// sequence number of the packet they acknowledge
+    pub seq: u64,
+    /// On acks, the number of packets acknowledged: the one numbered `seq`
+    /// and those just before it whose acks were thinned out on the way
+    pub covers: u64,
 }
 
 impl Packet {
-    pub fn draw<DB: DrawingBackend>(&self) -> Vec<DynElement<DB, (f64, f64)>> {
-        vec![
-            Rectangle::new(
-                [
-                    (self.coord.0, self.coord.1 - PKT_HEIGHT / 2.),
-                    (self.coord.0 + self.size, self.coord.1 + PKT_HEIGHT / 2.),
-                ],
-                self.style,
-            )
-            .into_dyn(),
-            Rectangle::new(
-                [
-                    (self.coord.0, self.coord.1 - PKT_HEIGHT / 2.),
-                    (self.coord.0 + self.size, self.coord.1 + PKT_HEIGHT / 2.),
-                ],
-                ShapeStyle::from(BLACK).stroke_width(2),
-            )
-            .into_dyn(),
-        ]
+    /// A data packet that is not marked, tracked or sequenced yet
+    pub fn new(size: f64, coord: Coord, addr: u16, flow: u16, style: Style) -> Self {
+        Self {
+            size,
+            coord,
+            addr,
+            flow,
+            style,
+            ce: false,
+            ack: false,
+            id: 0,
+            seq: 0,
+            covers: 0,
+        }
+    }
+
+    pub fn draw(&self, theme: &Theme) -> Vec<Shape> {
+        self.draw_in(theme.flow_style(self.flow, self.style), theme)
+    }
+
+    /// Draw a packet that is being dropped
+    pub fn draw_dropped(&self, theme: &Theme) -> Vec<Shape> {
+        match theme.drop {
+            Some(color) => self.draw_in(color.filled(), theme),
+            None => self.draw(theme),
+        }
+    }
+
+    fn draw_in(&self, style: Style, theme: &Theme) -> Vec<Shape> {
+        let (left, right) = (self.coord.0, self.coord.0 + self.size);
+        let (bottom, top) = (
+            self.coord.1 - PKT_HEIGHT / 2.,
+            self.coord.1 + PKT_HEIGHT / 2.,
+        );
+        let corners = (Coord(left, bottom), Coord(right, top));
+        let mut res = Vec::new();
+        if self.ack && theme.ack == AckStyle::Hollow {
+            res.push(Shape::Rect {
+                corners,
+                style: Style {
+                    filled: false,
+                    ..style.stroke_width(2)
+                },
+            });
+        } else {
+            res.push(Shape::Rect { corners, style });
+            if theme.packet_border > 0 {
+                res.push(Shape::Rect {
+                    corners,
+                    style: Style::from(theme.foreground).stroke_width(theme.packet_border),
+                });
+            }
+        }
+        if self.ce {
+            for points in [
+                vec![Coord(left, bottom), Coord(right, top)],
+                vec![Coord(left, top), Coord(right, bottom)],
+            ] {
+                res.push(Shape::Path {
+                    points,
+                    style: Style::from(theme.foreground).stroke_width(2),
+                });
+            }
+        }
+        res
     }
 }
 
@@ -67,6 +141,6 @@ pub trait Element {
     fn enqueue(&mut self, pkt: &Packet);
     fn get_pkts(&self) -> Vec<Packet>;
     fn tick(&mut self);
-    // fn draw<'a, 'b>(&'a self, chart: &'b mut Chart) -> Result<(), Box<dyn std::error::Error>>;
-    fn draw<DB: DrawingBackend>(&self) -> Vec<DynElement<DB, (f64, f64)>>;
+    /// What the element looks like right now
+    fn draw(&self, theme: &Theme) -> Vec<Shape>;
 }
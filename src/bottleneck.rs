@@ -1,21 +1,115 @@
 use crate::base::{Coord, Element, Packet, DATA_PKT_WIDTH, PKT_HEIGHT};
+use crate::check;
+use crate::scene::{Align, Shape, Style};
 use crate::simple_elems::Sink;
+use crate::theme::Theme;
 use crate::transport::Transport;
-use plotters::prelude::*;
 use std::cell::RefCell;
 use std::collections::VecDeque;
+use std::f64::consts::TAU;
 use std::rc::Rc;
 
-pub struct Bottleneck<N: Element> {
+/// How a bottleneck decides when to send the next packet
+#[derive(Clone, Debug)]
+pub enum Service {
+    /// Cycle through these numbers of ticks between successive packet
+    /// transmissions, regardless of packet size. Note: this is different from
+    /// mahimahi traces
+    Intersend(Vec<u64>),
+    /// Serialize this much packet width per tick, so a packet takes
+    /// `size / rate` ticks to send
+    ByteRate(f64),
+    /// Like `ByteRate`, but the rate changes over time
+    Scheduled(CapacitySchedule),
+}
+
+/// Link rate as a function of time, in packet width per tick
+#[derive(Clone, Debug)]
+pub enum Rate {
+    Constant(f64),
+    /// `(tick, rate)` pairs in increasing order of tick. Each rate holds from
+    /// its tick until the next one's. The rate is 0 before the first tick
+    Piecewise(Vec<(u64, f64)>),
+    /// `before` until tick `at`, and `after` from then on
+    Step {
+        at: u64,
+        before: f64,
+        after: f64,
+    },
+    /// Varies between `mean - amplitude` and `mean + amplitude` with the given
+    /// period in ticks. Negative rates count as 0
+    Sinusoid {
+        mean: f64,
+        amplitude: f64,
+        period: f64,
+    },
+}
+
+/// Time-varying capacity for `Service::Scheduled`
+#[derive(Clone, Debug)]
+pub struct CapacitySchedule {
+    pub rate: Rate,
+    /// (start, end) ticks during which the link sends nothing, regardless of
+    /// `rate`. `end` is exclusive
+    pub outages: Vec<(u64, u64)>,
+}
+
+impl CapacitySchedule {
+    /// Link rate at `tick`
+    pub fn rate_at(&self, tick: u64) -> f64 {
+        if self
+            .outages
+            .iter()
+            .any(|(start, end)| (*start..*end).contains(&tick))
+        {
+            return 0.;
+        }
+        let rate = match &self.rate {
+            Rate::Constant(rate) => *rate,
+            Rate::Piecewise(pieces) => pieces
+                .iter()
+                .take_while(|(start, _)| *start <= tick)
+                .last()
+                .map_or(0., |(_, rate)| *rate),
+            Rate::Step { at, before, after } => {
+                if tick < *at {
+                    *before
+                } else {
+                    *after
+                }
+            }
+            Rate::Sinusoid {
+                mean,
+                amplitude,
+                period,
+            } => mean + amplitude * (TAU * tick as f64 / period).sin(),
+        };
+        f64::max(0., rate)
+    }
+}
+
+/// What a bottleneck's buffer size counts. This determines both when packets
+/// are dropped and how wide the buffer is drawn
+#[derive(Clone, Copy, Debug)]
+pub enum BufferLimit {
+    /// At most this many packets, regardless of their size. Drawn as wide as
+    /// that many data packets
+    Packets(u64),
+    /// Packets whose sizes sum to at most this
+    Bytes(f64),
+}
+
+pub struct Bottleneck<N: Element + ?Sized> {
     /// Coords of the left center of the buffer
     coord: Coord,
-    /// Number of packets in buffer. Also determines visual size
-    bufsize: u64,
-    /// Number of ticks between successive packet transmissions. Note: this is
-    /// different from mahimahi traces
-    intersend_time: Vec<u64>,
-    /// Index into intersend time
+    /// Maximum size of the buffer. Also determines visual size
+    limit: BufferLimit,
+    service: Service,
+    /// Index into intersend time for `Service::Intersend`
     intersend_time_index: usize,
+    /// How much of the packet at the front has been serialized for
+    /// `Service::ByteRate`
+    amt_served: f64,
     pkts: VecDeque<Packet>,
     /// To determine when to send next packet
     time_since_last_deque: u64,
@@ -28,15 +122,18 @@ pub struct Bottleneck<N: Element> {
     dir: f64,
     /// Amount all packets have moved to animate dequeuing
     amt_moved: f64,
+    /// Packets that arrive to find at least this many packets queued get a CE
+    /// mark
+    ecn_threshold: Option<u64>,
+    /// Number of ticks so far, for `Service::Scheduled`
+    tick_num: u64,
     /// Should we draw the buffers?
     draw_buffer: bool,
-    /// Little buffer of packets so we can return in `draw`; an ugly hack that
-    /// is the result of poor choices with rust lifetimes. `enqueue` and `tick`
-    /// copy pkt over to this. `draw` messes with this and makes it dirty.
-    pkts_tmp_buffer: Vec<Packet>,
+    /// Should we show the current link rate?
+    show_rate: bool,
 }
 
-impl<N: Element> Bottleneck<N> {
+impl<N: Element + ?Sized> Bottleneck<N> {
     /// dir = true means left to right and false means right to left. Note,
     /// `next` can be empty when constructing and `next` can be filled
     /// afterward, before calling any other functions
@@ -56,17 +153,20 @@ impl<N: Element> Bottleneck<N> {
 
         Self {
             coord,
-            bufsize,
-            intersend_time,
+            limit: BufferLimit::Packets(bufsize),
+            service: Service::Intersend(intersend_time),
             intersend_time_index: 0,
+            amt_served: 0.,
             pkts: VecDeque::new(),
             time_since_last_deque: 0,
             dropper,
             next,
             dir: if dir { 1.0 } else { -1.0 },
             amt_moved: 0.,
+            ecn_threshold: None,
+            tick_num: 0,
             draw_buffer: true,
-            pkts_tmp_buffer: Vec::new(),
+            show_rate: false,
         }
     }
 
@@ -74,112 +174,459 @@ impl<N: Element> Bottleneck<N> {
         self.next = next;
     }
 
+    /// Replaces the buffer size given in `new`. Call this before enqueuing
+    /// any packets
+    pub fn set_buffer_limit(&mut self, limit: BufferLimit) {
+        self.limit = limit;
+    }
+
+    /// Width of the buffer when drawn
+    fn buffer_width(&self) -> f64 {
+        match self.limit {
+            BufferLimit::Packets(n) => n as f64 * DATA_PKT_WIDTH,
+            BufferLimit::Bytes(b) => b,
+        }
+    }
+
+    /// Whether the buffer has room for `pkt`
+    fn has_room(&self, pkt: &Packet) -> bool {
+        match self.limit {
+            BufferLimit::Packets(n) => self.pkts.len() < n as usize,
+            BufferLimit::Bytes(b) => {
+                // Tolerate floating point error in adding up sizes
+                self.occupancy() + pkt.size <= b + 1e-9
+            }
+        }
+    }
+
+    /// Sum of the sizes of all packets in the buffer
+    fn occupancy(&self) -> f64 {
+        self.pkts.iter().map(|p| p.size).sum()
+    }
+
+    /// Tell `check` how full the buffer is. This counts what is queued
+    /// rather than trusting `has_room`, so it catches overflows however they
+    /// happen
+    fn check_occupancy(&self) {
+        if !check::is_enabled() {
+            return;
+        }
+        match self.limit {
+            BufferLimit::Packets(n) => check::queue(self.pkts.len() as f64, n as f64),
+            BufferLimit::Bytes(b) => check::queue(self.occupancy(), b),
+        }
+    }
+
+    /// Replaces the whole service model, which `new` sets to `intersend_time`,
+    /// with intersend times, a byte rate or a capacity schedule
+    pub fn set_service(&mut self, service: Service) {
+        self.service = service;
+        self.intersend_time_index = 0;
+        self.amt_served = 0.;
+    }
+
+    /// Mark packets that arrive to find at least `threshold` packets queued,
+    /// so ECN-capable senders can back off before the buffer overflows.
+    /// `None` (the default) disables marking. The threshold is drawn as a red
+    /// line across the buffer
+    pub fn set_ecn_threshold(&mut self, threshold: Option<u64>) {
+        self.ecn_threshold = threshold;
+    }
+
     pub fn draw_buffer(&mut self, draw_buffer: bool) {
         self.draw_buffer = draw_buffer;
     }
+
+    /// Write the current link rate under the buffer
+    pub fn show_rate(&mut self, show_rate: bool) {
+        self.show_rate = show_rate;
+    }
+
+    /// Current link rate in packet width per tick, if it is defined by a rate
+    fn byte_rate(&self) -> Option<f64> {
+        match &self.service {
+            Service::Intersend(_) => None,
+            Service::ByteRate(rate) => Some(*rate),
+            Service::Scheduled(schedule) => Some(schedule.rate_at(self.tick_num)),
+        }
+    }
+
+    /// Current link rate in data packets per tick
+    pub fn current_rate(&self) -> f64 {
+        match &self.service {
+            Service::Intersend(intersend_time) => {
+                1. / intersend_time[self.intersend_time_index] as f64
+            }
+            _ => self.byte_rate().unwrap() / DATA_PKT_WIDTH,
+        }
+    }
+
+    /// Send the packet at the front to the next element
+    fn dequeue(&mut self) {
+        let mut popped = self.pkts.pop_front().unwrap();
+        // It has fully drained out of the buffer by now
+        popped.coord.0 += popped.size * self.dir;
+        match self.next.get(popped.addr as usize) {
+            Some(next) => next.borrow_mut().enqueue(&popped),
+            // Nowhere to send it
+            None => {
+                check::dropped(&popped);
+                self.dropper.enqueue(&popped);
+            }
+        }
+        for pkt in &mut self.pkts {
+            pkt.coord.0 += popped.size * self.dir;
+        }
+    }
+
+    /// Where packets leave the buffer
+    pub fn get_dequeue_coord(&self) -> Coord {
+        self.coord.add(Coord(self.buffer_width() * self.dir, 0.))
+    }
 }
 
-impl<N: Element> Element for Bottleneck<N> {
+impl<N: Element + ?Sized> Element for Bottleneck<N> {
     fn get_enqueue_coord(&self) -> Coord {
-        assert!(self.pkts.len() <= self.bufsize as usize);
+        assert!(match self.limit {
+            BufferLimit::Packets(n) => self.pkts.len() <= n as usize,
+            BufferLimit::Bytes(b) => self.occupancy() <= b + 1e-9,
+        });
         self.coord.sub(Coord(DATA_PKT_WIDTH, 0.))
     }
 
     fn enqueue(&mut self, pkt: &Packet) {
-        if self.pkts.len() < self.bufsize as usize {
-            let mut pkt = pkt.clone();
-            let bufwidth: f64 = self.pkts.iter().map(|p| p.size).sum();
+        if self.has_room(pkt) {
+            let mut pkt = *pkt;
+            // Packets are drawn rightward from `coord`, so in a left to right
+            // buffer the front edge is `size` to the right of `coord`
+            let bufwidth = self.occupancy();
             pkt.coord = self
-                .coord
+                .get_dequeue_coord()
                 .sub(Coord(bufwidth * self.dir, 0.))
-                .add(Coord(self.bufsize as f64 * DATA_PKT_WIDTH * self.dir, 0.))
-                .sub(Coord(pkt.size * self.dir, 0.));
+                .sub(Coord(pkt.size * (self.dir + 1.) / 2., 0.));
+            if let Some(threshold) = self.ecn_threshold {
+                pkt.ce |= self.pkts.len() as u64 >= threshold;
+            }
             self.pkts.push_back(pkt);
         } else {
+            check::dropped(pkt);
             self.dropper.enqueue(pkt);
         }
-
-        // Ugly trick for lifetimes
-        self.pkts_tmp_buffer = self
-            .pkts
-            .iter()
-            .map(|x| {
-                let mut y = x.clone();
-                y.coord.0 += self.amt_moved;
-                y
-            })
-            .collect();
+        self.check_occupancy();
     }
 
     fn get_pkts(&self) -> Vec<Packet> {
-        self.pkts.iter().map(|p| *p).collect()
+        self.pkts.iter().copied().collect()
     }
 
     fn tick(&mut self) {
         self.time_since_last_deque += 1;
-        if self.time_since_last_deque >= self.intersend_time[self.intersend_time_index]
-            && self.pkts.len() > 0
-        {
-            self.time_since_last_deque = 0;
-            let popped = self.pkts.pop_front().unwrap();
-            self.next[popped.addr as usize]
-                .borrow_mut()
-                .enqueue(&popped);
-            for mut pkt in &mut self.pkts {
-                pkt.coord.0 += popped.size * self.dir;
+        match &self.service {
+            Service::Intersend(intersend_time) => {
+                if self.time_since_last_deque >= intersend_time[self.intersend_time_index]
+                    && !self.pkts.is_empty()
+                {
+                    self.time_since_last_deque = 0;
+                    self.intersend_time_index =
+                        (self.intersend_time_index + 1) % intersend_time.len();
+                    self.dequeue();
+                }
+            }
+            Service::ByteRate(_) | Service::Scheduled(_) => {
+                if !self.pkts.is_empty() {
+                    self.amt_served += self.byte_rate().unwrap();
+                }
+                // Fast links can send multiple packets in a tick
+                while let Some(front) = self.pkts.front() {
+                    if self.amt_served < front.size {
+                        break;
+                    }
+                    self.amt_served -= front.size;
+                    self.time_since_last_deque = 0;
+                    self.dequeue();
+                }
+                if self.pkts.is_empty() {
+                    // Idle links don't accumulate credit
+                    self.amt_served = 0.;
+                }
             }
-            self.amt_moved = 0.;
-            self.intersend_time_index = (self.intersend_time_index + 1) % self.intersend_time.len();
-        }
-        // Move the packets a little to indicate progress
-        if let Some(_front) = self.pkts.front() {
-            self.amt_moved = 0.;
-            // self.amt_moved = front.size * self.time_since_last_deque as f64 / self.intersend_time as f64;
-        } else {
-            self.amt_moved = 0.;
         }
+
+        // Move the packets a little to indicate progress in serializing the
+        // packet at the front
+        self.amt_moved = match (&self.service, self.pkts.front()) {
+            (Service::Intersend(intersend_time), Some(front)) => {
+                let intersend = intersend_time[self.intersend_time_index];
+                if intersend > 0 {
+                    front.size * f64::min(1., self.time_since_last_deque as f64 / intersend as f64)
+                } else {
+                    0.
+                }
+            }
+            (_, Some(_)) => self.amt_served,
+            (_, None) => 0.,
+        };
         self.dropper.tick();
+        self.check_occupancy();
+        self.tick_num += 1;
+    }
+
+    fn draw(&self, theme: &Theme) -> Vec<Shape> {
+        let size = self.buffer_width();
+        let mut res = Vec::new();
+        if self.draw_buffer {
+            res.push(Shape::Path {
+                points: vec![
+                    Coord(self.coord.0, self.coord.1 - PKT_HEIGHT * 0.55 * self.dir),
+                    Coord(
+                        self.coord.0 + size * self.dir,
+                        self.coord.1 - PKT_HEIGHT * 0.55 * self.dir,
+                    ),
+                    Coord(
+                        self.coord.0 + size * self.dir,
+                        self.coord.1 + PKT_HEIGHT * 0.55 * self.dir,
+                    ),
+                    Coord(self.coord.0, self.coord.1 + PKT_HEIGHT * 0.55 * self.dir),
+                ],
+                style: Style::from(theme.foreground),
+            });
+        }
+        if let Some(threshold) = self.ecn_threshold {
+            let x = self.get_dequeue_coord().0 - threshold as f64 * DATA_PKT_WIDTH * self.dir;
+            res.push(Shape::Path {
+                points: vec![
+                    Coord(x, self.coord.1 - PKT_HEIGHT * 0.75),
+                    Coord(x, self.coord.1 + PKT_HEIGHT * 0.75),
+                ],
+                style: Style::from(theme.mark).stroke_width(2),
+            });
+        }
+
+        if self.show_rate {
+            let center = self
+                .coord
+                .add(Coord(size * self.dir / 2., -PKT_HEIGHT * 1.1));
+            res.push(theme.text(
+                format!("{:.2} pkts/tick", self.current_rate()),
+                center,
+                Align::Center,
+            ));
+        }
+
+        // Packets slide forward as the one at the front is dequeued
+        for pkt in &self.pkts {
+            let mut pkt = *pkt;
+            pkt.coord.0 += self.amt_moved * self.dir;
+            res.extend(pkt.draw(theme));
+        }
+        for pkt in self.dropper.get_pkts() {
+            res.extend(pkt.draw_dropped(theme));
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::Color;
+    use crate::simple_elems::Recorder;
+    use crate::simulation::Simulation;
+
+    fn pkt() -> Packet {
+        Packet::new(
+            DATA_PKT_WIDTH,
+            Coord(0., 0.),
+            0,
+            0,
+            Style::from(Color::RED).filled(),
+        )
+    }
 
-        // Ugly trick for lifetimes
-        self.pkts_tmp_buffer = self
-            .pkts
+    /// A bottleneck that sends straight to a recorder
+    struct Setup {
+        sim: Simulation,
+        bottleneck: Rc<RefCell<Bottleneck<Recorder>>>,
+        recorder: Rc<RefCell<Recorder>>,
+    }
+
+    fn setup(bufsize: u64, intersend: u64) -> Setup {
+        let recorder = Rc::new(RefCell::new(Recorder::new(Coord(10., 0.))));
+        let bottleneck = Rc::new(RefCell::new(Bottleneck::new(
+            Coord(0., 0.),
+            bufsize,
+            vec![intersend],
+            vec![recorder.clone()],
+            true,
+        )));
+        let mut sim = Simulation::new();
+        sim.add("bottleneck", bottleneck.clone());
+        sim.add("recorder", recorder.clone());
+        Setup {
+            sim,
+            bottleneck,
+            recorder,
+        }
+    }
+
+    #[test]
+    fn intersend_spaces_departures() {
+        let Setup {
+            mut sim,
+            bottleneck,
+            recorder,
+        } = setup(8, 5);
+        for _ in 0..4 {
+            bottleneck.borrow_mut().enqueue(&pkt());
+        }
+        let metrics = sim.run(40);
+        let ticks: Vec<_> = recorder.borrow().received().iter().map(|r| r.0).collect();
+        assert_eq!(ticks.len(), 4);
+        assert!(ticks.windows(2).all(|w| w[1] - w[0] == 5));
+        assert_eq!(metrics.occupancy("bottleneck").unwrap().last, 0);
+    }
+
+    #[test]
+    fn drops_when_full() {
+        let Setup {
+            mut sim,
+            bottleneck,
+            recorder,
+        } = setup(3, 5);
+        for _ in 0..5 {
+            bottleneck.borrow_mut().enqueue(&pkt());
+        }
+        assert_eq!(bottleneck.borrow().get_pkts().len(), 3);
+        sim.run(40);
+        assert_eq!(recorder.borrow().received().len(), 3);
+    }
+
+    #[test]
+    fn marks_above_threshold() {
+        let Setup {
+            mut sim,
+            bottleneck,
+            recorder,
+        } = setup(8, 5);
+        bottleneck.borrow_mut().set_ecn_threshold(Some(2));
+        for _ in 0..4 {
+            bottleneck.borrow_mut().enqueue(&pkt());
+        }
+        sim.run(40);
+        let marks: Vec<_> = recorder
+            .borrow()
+            .received()
             .iter()
-            .map(|x| {
-                let mut y = x.clone();
-                y.coord.0 += self.amt_moved;
-                y
-            })
+            .map(|r| r.1.ce)
             .collect();
+        assert_eq!(marks, vec![false, false, true, true]);
     }
 
-    fn draw<DB: DrawingBackend>(&self) -> Vec<DynElement<DB, (f64, f64)>> {
-        let size = self.bufsize as f64 * DATA_PKT_WIDTH;
-        let buffer = PathElement::new(
-            vec![
-                (self.coord.0, self.coord.1 - PKT_HEIGHT * 0.55 * self.dir),
-                (
-                    self.coord.0 + size * self.dir,
-                    self.coord.1 - PKT_HEIGHT * 0.55 * self.dir,
-                ),
-                (
-                    self.coord.0 + size * self.dir,
-                    self.coord.1 + PKT_HEIGHT * 0.55 * self.dir,
-                ),
-                (self.coord.0, self.coord.1 + PKT_HEIGHT * 0.55 * self.dir),
-            ],
-            BLACK,
-        );
-        let mut res = if self.draw_buffer {
-            vec![buffer.into_dyn()]
-        } else {
-            Vec::new()
-        };
+    fn sized(size: f64) -> Packet {
+        Packet { size, ..pkt() }
+    }
 
-        // `enqueue` and `tick` nicely modified this for us
-        for pkt in &self.pkts_tmp_buffer {
-            res.extend(pkt.draw());
+    #[test]
+    fn byte_rate_serializes_by_size() {
+        let Setup {
+            mut sim,
+            bottleneck,
+            recorder,
+        } = setup(8, 5);
+        bottleneck
+            .borrow_mut()
+            .set_service(Service::ByteRate(0.125));
+        for size in [0.25, 0.5, 0.125] {
+            bottleneck.borrow_mut().enqueue(&sized(size));
         }
-        res.extend(self.dropper.draw());
-        res
+        sim.run(10);
+        // Each packet leaves `size / rate` ticks after the one before it
+        let ticks: Vec<_> = recorder.borrow().received().iter().map(|r| r.0).collect();
+        assert_eq!(ticks, vec![1, 5, 6]);
+    }
+
+    #[test]
+    fn byte_rate_forgets_credit_when_idle() {
+        let Setup {
+            mut sim,
+            bottleneck,
+            recorder,
+        } = setup(8, 5);
+        bottleneck.borrow_mut().set_service(Service::ByteRate(0.2));
+        bottleneck.borrow_mut().enqueue(&sized(0.25));
+        sim.run(10);
+        // The first packet left with 0.15 to spare, which must not speed up
+        // the next one
+        bottleneck.borrow_mut().enqueue(&sized(0.25));
+        sim.run(10);
+        let ticks: Vec<_> = recorder.borrow().received().iter().map(|r| r.0).collect();
+        assert_eq!(ticks, vec![1, 11]);
+    }
+
+    #[test]
+    fn byte_limit_admits_by_size() {
+        check::enable();
+        let Setup { bottleneck, .. } = setup(8, 5);
+        bottleneck
+            .borrow_mut()
+            .set_buffer_limit(BufferLimit::Bytes(1.));
+        for size in [0.5, 0.25, 0.5, 0.25] {
+            let mut pkt = sized(size);
+            check::created(&mut pkt);
+            bottleneck.borrow_mut().enqueue(&pkt);
+        }
+        // The second 0.5 doesn't fit, but the 0.25 after it does
+        let bottleneck = bottleneck.borrow();
+        let sizes: Vec<_> = bottleneck.get_pkts().iter().map(|p| p.size).collect();
+        assert_eq!(sizes, vec![0.5, 0.25, 0.25]);
+        let dropped = bottleneck.dropper.get_pkts();
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(dropped[0].size, 0.5);
+        let report = check::finish(bottleneck.get_pkts()).unwrap();
+        assert_eq!(report.num_dropped, 1);
+    }
+
+    #[test]
+    fn sends_nothing_during_outage() {
+        let Setup {
+            mut sim,
+            bottleneck,
+            recorder,
+        } = setup(8, 5);
+        bottleneck
+            .borrow_mut()
+            .set_service(Service::Scheduled(CapacitySchedule {
+                rate: Rate::Constant(DATA_PKT_WIDTH),
+                outages: vec![(0, 50)],
+            }));
+        bottleneck.borrow_mut().enqueue(&pkt());
+        let metrics = sim.run(50);
+        assert!(recorder.borrow().received().is_empty());
+        assert_eq!(metrics.occupancy("bottleneck").unwrap().mean, 1.);
+        sim.run(1);
+        assert_eq!(recorder.borrow().received().len(), 1);
+    }
+
+    #[test]
+    fn checks_occupancy_not_admission() {
+        check::enable();
+        let Setup { bottleneck, .. } = setup(4, 5);
+        for _ in 0..4 {
+            bottleneck.borrow_mut().enqueue(&pkt());
+        }
+        // Shrinking a full buffer leaves it over its new limit
+        bottleneck
+            .borrow_mut()
+            .set_buffer_limit(BufferLimit::Packets(2));
+        bottleneck.borrow_mut().tick();
+        let in_flight = bottleneck.borrow().get_pkts();
+        let report = check::finish(in_flight).unwrap_err();
+        assert_eq!(
+            report.violations[0],
+            check::Violation::Overflow {
+                occupancy: 4.,
+                limit: 2.
+            }
+        );
     }
 }
@@ -0,0 +1,354 @@
+//! Optional bookkeeping that follows every packet from creation to delivery or
+//! drop, and reports anything that breaks conservation. Checking is off unless
+//! `enable` is called, and applies to the current thread only
+use crate::base::Packet;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+
+thread_local! {
+    static LEDGER: RefCell<Option<Ledger>> = const { RefCell::new(None) };
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Fate {
+    InFlight,
+    Delivered,
+    Dropped,
+}
+
+#[derive(Default)]
+struct Ledger {
+    /// Flow and fate of every packet created so far, by id
+    pkts: HashMap<u64, (u16, Fate)>,
+    num_created: u64,
+    violations: Vec<Violation>,
+}
+
+impl Ledger {
+    /// Move a tracked packet to `fate`, noting if it already had one
+    fn settle(&mut self, pkt: &Packet, fate: Fate) {
+        let Some((_, current)) = self.pkts.get_mut(&pkt.id) else {
+            return;
+        };
+        match (*current, fate) {
+            (Fate::InFlight, _) => *current = fate,
+            // Dropped packets end up in a sink too
+            (Fate::Dropped, Fate::Delivered) => {}
+            _ => self.violations.push(Violation::Duplicated {
+                id: pkt.id,
+                flow: pkt.flow,
+            }),
+        }
+    }
+}
+
+/// Something that should never happen in a simulation
+#[derive(Clone, Debug, PartialEq)]
+pub enum Violation {
+    /// The packet disappeared without being delivered or dropped
+    Lost { id: u64, flow: u16 },
+    /// More than one copy of the packet was delivered, dropped or in flight
+    Duplicated { id: u64, flow: u16 },
+    /// A queue held more than its limit
+    Overflow { occupancy: f64, limit: f64 },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Violation::Lost { id, flow } => {
+                write!(f, "packet {} of flow {} was lost without a drop", id, flow)
+            }
+            Violation::Duplicated { id, flow } => {
+                write!(f, "packet {} of flow {} was duplicated", id, flow)
+            }
+            Violation::Overflow { occupancy, limit } => {
+                write!(f, "queue held {} with a limit of {}", occupancy, limit)
+            }
+        }
+    }
+}
+
+/// What `finish` found
+#[derive(Debug)]
+pub struct Report {
+    pub num_created: u64,
+    pub num_delivered: u64,
+    pub num_dropped: u64,
+    pub num_in_flight: u64,
+    pub violations: Vec<Violation>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} packets created: {} delivered, {} dropped, {} in flight",
+            self.num_created, self.num_delivered, self.num_dropped, self.num_in_flight
+        )?;
+        for violation in &self.violations {
+            writeln!(f, "  {}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for Report {}
+
+/// Start checking on this thread, forgetting anything checked so far
+pub fn enable() {
+    LEDGER.with(|ledger| *ledger.borrow_mut() = Some(Ledger::default()));
+}
+
+pub fn is_enabled() -> bool {
+    LEDGER.with(|ledger| ledger.borrow().is_some())
+}
+
+/// Give a newly created packet an id so we can follow it
+pub(crate) fn created(pkt: &mut Packet) {
+    LEDGER.with(|ledger| {
+        if let Some(ledger) = ledger.borrow_mut().as_mut() {
+            ledger.num_created += 1;
+            pkt.id = ledger.num_created;
+            ledger.pkts.insert(pkt.id, (pkt.flow, Fate::InFlight));
+        }
+    });
+}
+
+/// The packet reached its final destination
+pub(crate) fn delivered(pkt: &Packet) {
+    LEDGER.with(|ledger| {
+        if let Some(ledger) = ledger.borrow_mut().as_mut() {
+            ledger.settle(pkt, Fate::Delivered);
+        }
+    });
+}
+
+/// The packet was dropped on purpose
+pub(crate) fn dropped(pkt: &Packet) {
+    LEDGER.with(|ledger| {
+        if let Some(ledger) = ledger.borrow_mut().as_mut() {
+            ledger.settle(pkt, Fate::Dropped);
+        }
+    });
+}
+
+/// A queue now holds `occupancy`. Units are up to the queue
+pub(crate) fn queue(occupancy: f64, limit: f64) {
+    LEDGER.with(|ledger| {
+        if let Some(ledger) = ledger.borrow_mut().as_mut() {
+            if occupancy > limit + 1e-9 {
+                ledger
+                    .violations
+                    .push(Violation::Overflow { occupancy, limit });
+            }
+        }
+    });
+}
+
+/// End a checked run. `in_flight` are the packets still inside elements;
+/// every other packet must have been delivered or dropped exactly once.
+/// Checking stays on, with a fresh ledger, for the next run
+pub fn finish(in_flight: impl IntoIterator<Item = Packet>) -> Result<Report, Report> {
+    let ledger = LEDGER.with(|ledger| ledger.borrow_mut().replace(Ledger::default()));
+    let Some(mut ledger) = ledger else {
+        panic!("check::finish called without check::enable");
+    };
+
+    let mut seen = HashSet::new();
+    for pkt in in_flight {
+        let Some((_, fate)) = ledger.pkts.get(&pkt.id) else {
+            continue;
+        };
+        if !seen.insert(pkt.id) || *fate != Fate::InFlight {
+            ledger.violations.push(Violation::Duplicated {
+                id: pkt.id,
+                flow: pkt.flow,
+            });
+        }
+    }
+
+    let mut report = Report {
+        num_created: ledger.num_created,
+        num_delivered: 0,
+        num_dropped: 0,
+        num_in_flight: seen.len() as u64,
+        violations: ledger.violations,
+    };
+    let mut ids: Vec<_> = ledger.pkts.keys().copied().collect();
+    ids.sort();
+    for id in ids {
+        let (flow, fate) = ledger.pkts[&id];
+        match fate {
+            Fate::Delivered => report.num_delivered += 1,
+            Fate::Dropped => report.num_dropped += 1,
+            Fate::InFlight if !seen.contains(&id) => {
+                report.violations.push(Violation::Lost { id, flow })
+            }
+            Fate::InFlight => {}
+        }
+    }
+
+    if report.violations.is_empty() {
+        Ok(report)
+    } else {
+        Err(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base::{Coord, Element, DATA_PKT_WIDTH};
+    use crate::bottleneck::Bottleneck;
+    use crate::scene::{Color, Shape, Style};
+    use crate::simple_elems::{Acker, Sink};
+    use crate::theme::Theme;
+    use crate::traffic::{Pattern, TrafficSource};
+    use crate::transport::Transport;
+    use std::rc::Rc;
+
+    /// Mishandles every packet it is given
+    enum Faulty {
+        Swallow,
+        Duplicate,
+    }
+
+    impl Element for Faulty {
+        fn get_enqueue_coord(&self) -> Coord {
+            Coord(0., 0.)
+        }
+        fn enqueue(&mut self, pkt: &Packet) {
+            if let Faulty::Duplicate = self {
+                delivered(pkt);
+                delivered(pkt);
+            }
+        }
+        fn get_pkts(&self) -> Vec<Packet> {
+            Vec::new()
+        }
+        fn tick(&mut self) {}
+        fn draw(&self, _theme: &Theme) -> Vec<Shape> {
+            Vec::new()
+        }
+    }
+
+    fn template() -> Packet {
+        Packet::new(
+            DATA_PKT_WIDTH,
+            Coord(0., 0.),
+            0,
+            0,
+            Style::from(Color::RED).filled(),
+        )
+    }
+
+    fn cbr() -> Pattern {
+        Pattern::Cbr { intersend: 1 }
+    }
+
+    #[test]
+    fn overloaded_path_conserves_packets() {
+        enable();
+        let sink = Rc::new(RefCell::new(Sink {
+            coord: Coord(0., 0.),
+        }));
+        let returnpath = Rc::new(RefCell::new(Transport::new(4, sink)));
+        let acker = Rc::new(RefCell::new(Acker {
+            rcv_coord: Coord(10., 0.),
+            snd_coord: Coord(10., 1.),
+            next: returnpath.clone(),
+        }));
+        let departure = Rc::new(RefCell::new(Transport::new(4, acker)));
+        let bottleneck = Rc::new(RefCell::new(Bottleneck::new(
+            Coord(5., 0.),
+            4,
+            vec![3],
+            vec![departure.clone()],
+            true,
+        )));
+        let mut source = TrafficSource::new(cbr(), template(), bottleneck.clone(), 0);
+        for _ in 0..200 {
+            source.tick();
+            bottleneck.borrow_mut().tick();
+            departure.borrow_mut().tick();
+            returnpath.borrow_mut().tick();
+        }
+
+        let mut in_flight = bottleneck.borrow().get_pkts();
+        in_flight.extend(departure.borrow().get_pkts());
+        in_flight.extend(returnpath.borrow().get_pkts());
+        let report = finish(in_flight).unwrap();
+        assert_eq!(report.num_created, 200);
+        assert!(report.num_dropped > 0);
+        assert!(report.num_delivered > 0);
+        assert_eq!(
+            report.num_delivered + report.num_dropped + report.num_in_flight,
+            200
+        );
+    }
+
+    #[test]
+    fn presets_conserve_packets() {
+        use crate::{elem_anim, fair_anim, path_anim, presets, two_bottlenecks_anim};
+        enable();
+        for mut config in presets::path() {
+            config.num_ticks = 300;
+            path_anim::path_frames(&config).unwrap();
+        }
+        for mut config in presets::elem() {
+            config.num_ticks = 300;
+            elem_anim::elem_frames(&config).unwrap();
+        }
+        for mut config in presets::fair() {
+            config.num_ticks = 300;
+            fair_anim::fair_frames(&config).unwrap();
+        }
+        for mut config in presets::two_bottlenecks() {
+            config.num_ticks = 300;
+            two_bottlenecks_anim::two_bottlenecks_frames(&config).unwrap();
+        }
+        // Each run settled its own packets
+        assert_eq!(finish(Vec::new()).unwrap().num_created, 0);
+    }
+
+    #[test]
+    fn reports_lost_packets() {
+        enable();
+        let faulty = Rc::new(RefCell::new(Faulty::Swallow));
+        let mut source = TrafficSource::new(cbr(), template(), faulty, 0);
+        source.tick();
+        let report = finish(Vec::new()).unwrap_err();
+        assert_eq!(report.violations, vec![Violation::Lost { id: 1, flow: 0 }]);
+    }
+
+    #[test]
+    fn reports_duplicates() {
+        enable();
+        let faulty = Rc::new(RefCell::new(Faulty::Duplicate));
+        let mut source = TrafficSource::new(cbr(), template(), faulty, 0);
+        source.tick();
+        let report = finish(Vec::new()).unwrap_err();
+        assert_eq!(
+            report.violations,
+            vec![Violation::Duplicated { id: 1, flow: 0 }]
+        );
+    }
+
+    #[test]
+    fn reports_overflow() {
+        enable();
+        queue(3., 4.);
+        queue(5., 4.);
+        let report = finish(Vec::new()).unwrap_err();
+        assert_eq!(
+            report.violations,
+            vec![Violation::Overflow {
+                occupancy: 5.,
+                limit: 4.
+            }]
+        );
+    }
+}
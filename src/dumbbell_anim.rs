@@ -0,0 +1,305 @@
+//! Animate N flows sharing a single bottleneck
+use crate::annotation::{Annotations, Caption};
+use crate::base::{Coord, Element, Packet, DATA_PKT_WIDTH, PKT_HEIGHT};
+use crate::bottleneck::{Bottleneck, CapacitySchedule, Service};
+use crate::check;
+use crate::layout::{Layout, Topology};
+use crate::node::{Diagram, NodeKind};
+use crate::output::{Animation, OutputConfig};
+use crate::pool::Pool;
+use crate::scene::{Color, Style};
+use crate::simple_elems::{Acker, FctSink};
+use crate::traffic::{Pattern, TrafficSource};
+use crate::transport::Transport;
+use std::cell::RefCell;
+use std::error::Error;
+use std::rc::Rc;
+
+/// Configuration of one flow in a preset with many flows
+#[derive(Clone)]
+pub struct FlowConfig {
+    /// When the flow sends packets
+    pub pattern: Pattern,
+    /// Round trip propagation delay, in the units `Transport::new` takes. It
+    /// is split among the transports along the flow's path
+    pub rtt: u64,
+    /// Color of the flow's packets
+    pub color: Color,
+    /// Tick at which the flow starts sending
+    pub start: u64,
+    /// Tick at which the flow stops sending, if it ever does
+    pub stop: Option<u64>,
+    /// Number of packets to transfer, if finite. The flow's completion time
+    /// is shown once they have all been acked
+    pub size: Option<u64>,
+}
+
+impl Default for FlowConfig {
+    /// A packet every 10 ticks from the first tick on, forever
+    fn default() -> Self {
+        Self {
+            pattern: Pattern::Cbr { intersend: 10 },
+            rtt: 40,
+            color: Color::BLACK,
+            start: 0,
+            stop: None,
+            size: None,
+        }
+    }
+}
+
+impl FlowConfig {
+    /// Source that sends copies of `template` to `next` as configured
+    pub(crate) fn source<N: Element + ?Sized>(
+        &self,
+        template: Packet,
+        next: Rc<RefCell<N>>,
+        seed: u64,
+    ) -> TrafficSource<N> {
+        let mut source = TrafficSource::new(self.pattern.clone(), template, next, seed);
+        source.set_start(self.start);
+        if let Some(stop) = self.stop {
+            source.set_stop(stop);
+        }
+        if let Some(size) = self.size {
+            source.set_size(size);
+        }
+        source
+    }
+
+    /// Sink for the flow's acks that records its completion time
+    pub(crate) fn sink(&self, coord: Coord) -> FctSink {
+        FctSink::new(coord, self.start, self.size)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct DumbbellAnimConfig {
+    /// Where to output the .gif file
+    pub filename: String,
+    /// Buffer size for both directions
+    pub bufsize: u64,
+    /// Bottleneck link rate
+    pub bottleneck_intersend: u64,
+    /// If given, the forward bottleneck follows this instead of
+    /// `bottleneck_intersend` and shows its current rate
+    pub capacity: Option<CapacitySchedule>,
+    /// One entry per flow
+    pub flows: Vec<FlowConfig>,
+    /// Number of ticks to animate
+    pub num_ticks: u64,
+    /// Should we draw the current tick?
+    pub show_clock: bool,
+    /// Text to show between given ticks
+    pub captions: Vec<Caption>,
+    /// Size, frame rate and chart ranges of the output
+    pub output: OutputConfig,
+}
+
+pub fn dumbbell_anim(config: &DumbbellAnimConfig, pool: &Pool) -> Result<(), Box<dyn Error>> {
+    dumbbell_frames(config)?.save_gif(&config.filename, pool)
+}
+
+/// Simulate every tick of the animation
+pub fn dumbbell_frames(config: &DumbbellAnimConfig) -> Result<Animation, Box<dyn Error>> {
+    let theme = &config.output.theme;
+    let layout = Layout::new(&Topology {
+        hops: vec![config.bufsize],
+        ret_bufsize: config.bufsize,
+        num_flows: config.flows.len(),
+        flow_sep: PKT_HEIGHT * 6.,
+    });
+    let mut anim = Animation::new(config.output.canvas(
+        (1600, layout.canvas_height()),
+        layout.x_range.clone(),
+        layout.y_range.clone(),
+    ));
+
+    // Shared bottlenecks. We will populate `next` after constructing the flows
+    let ret_bottleneck = Rc::new(RefCell::new(Bottleneck::<dyn Element>::new(
+        layout.ret_hop,
+        config.bufsize,
+        vec![config.bottleneck_intersend],
+        vec![],
+        false,
+    )));
+    let bottleneck = Rc::new(RefCell::new(Bottleneck::<dyn Element>::new(
+        layout.hops[0],
+        config.bufsize,
+        vec![config.bottleneck_intersend],
+        vec![],
+        true,
+    )));
+
+    // (arrival, departure, acker, returnpath1, returnpath2, sink) for each flow
+    let mut flows = Vec::new();
+    for (i, flow) in config.flows.iter().enumerate() {
+        // Same proportions as the other animations
+        let (arrival, departure, returnpath1) = (flow.rtt * 2 / 5, flow.rtt / 5, flow.rtt / 5);
+        let returnpath2 = flow.rtt - arrival - departure - returnpath1;
+
+        let sink = Rc::new(RefCell::new(flow.sink(layout.sinks[i])));
+        let returnpath2 = Rc::new(RefCell::new(Transport::new(returnpath2, sink.clone())));
+        let returnpath1 = Rc::new(RefCell::new(Transport::new(
+            returnpath1,
+            ret_bottleneck.clone(),
+        )));
+        let acker = Rc::new(RefCell::new(Acker {
+            rcv_coord: layout.receivers[i].0,
+            snd_coord: layout.receivers[i].1,
+            next: returnpath1.clone(),
+        }));
+        let departure = Rc::new(RefCell::new(Transport::new(departure, acker.clone())));
+        let arrival = Rc::new(RefCell::new(Transport::new(arrival, bottleneck.clone())));
+        flows.push((arrival, departure, acker, returnpath1, returnpath2, sink));
+    }
+
+    if let Some(capacity) = &config.capacity {
+        let mut bottleneck = bottleneck.borrow_mut();
+        bottleneck.set_service(Service::Scheduled(capacity.clone()));
+        bottleneck.show_rate(true);
+    }
+
+    bottleneck.borrow_mut().set_next(
+        flows
+            .iter()
+            .map(|f| f.1.clone() as Rc<RefCell<dyn Element>>)
+            .collect(),
+    );
+    ret_bottleneck.borrow_mut().set_next(
+        flows
+            .iter()
+            .map(|f| f.4.clone() as Rc<RefCell<dyn Element>>)
+            .collect(),
+    );
+
+    let mut sources: Vec<_> = config
+        .flows
+        .iter()
+        .zip(&flows)
+        .enumerate()
+        .map(|(i, (flow, (arrival, ..)))| {
+            let template = Packet::new(
+                DATA_PKT_WIDTH,
+                layout.senders[i],
+                i as u16,
+                i as u16,
+                Style::from(flow.color).filled(),
+            );
+            flow.source(template, arrival.clone(), i as u64)
+        })
+        .collect();
+
+    let mut diagram = Diagram::new();
+    diagram.node(NodeKind::Router, bottleneck.borrow().get_dequeue_coord());
+    diagram.node(
+        NodeKind::Router,
+        ret_bottleneck.borrow().get_dequeue_coord(),
+    );
+    for (i, (arrival, departure, _, returnpath1, returnpath2, _)) in flows.iter().enumerate() {
+        diagram.node(NodeKind::Host, layout.host_coord(i));
+        diagram.node(NodeKind::Receiver, layout.receiver_coord(i));
+        diagram.link(&*arrival.borrow(), layout.senders[i]);
+        diagram.link(
+            &*departure.borrow(),
+            bottleneck.borrow().get_dequeue_coord(),
+        );
+        diagram.link(&*returnpath1.borrow(), layout.receivers[i].1);
+        diagram.link(
+            &*returnpath2.borrow(),
+            ret_bottleneck.borrow().get_dequeue_coord(),
+        );
+    }
+
+    let mut annotations = Annotations::new();
+    annotations.label(
+        "Bottleneck",
+        bottleneck.borrow().get_enqueue_coord(),
+        Coord(
+            DATA_PKT_WIDTH * (config.bufsize as f64 / 2. + 1.),
+            PKT_HEIGHT * 1.1,
+        ),
+    );
+    annotations.legend(
+        layout.legend_coord(),
+        config
+            .flows
+            .iter()
+            .enumerate()
+            .map(|(i, f)| {
+                (
+                    format!("Flow {} (RTT {})", i + 1, f.rtt),
+                    Style::from(f.color).filled(),
+                )
+            })
+            .collect(),
+    );
+    if config.show_clock {
+        annotations.clock(layout.clock_coord());
+    }
+    annotations.captions(layout.caption_coord(), config.captions.clone());
+
+    for tick in 0..config.num_ticks {
+        let mut frame = diagram.draw(theme);
+
+        // Produce packets
+        for (source, (arrival, ..)) in sources.iter_mut().zip(&flows) {
+            source.tick();
+            let mut arrival = arrival.borrow_mut();
+            arrival.tick();
+            frame.extend(arrival.draw(theme));
+        }
+
+        // Shared bottlenecks
+        {
+            let mut bottleneck = bottleneck.borrow_mut();
+            bottleneck.tick();
+            frame.extend(bottleneck.draw(theme));
+        }
+
+        {
+            let mut ret_bottleneck = ret_bottleneck.borrow_mut();
+            ret_bottleneck.tick();
+            frame.extend(ret_bottleneck.draw(theme));
+        }
+
+        for (_, departure, acker, returnpath1, returnpath2, sink) in &flows {
+            let mut departure = departure.borrow_mut();
+            departure.tick();
+            frame.extend(departure.draw(theme));
+
+            let mut acker = acker.borrow_mut();
+            acker.tick();
+            frame.extend(acker.draw(theme));
+
+            let mut returnpath1 = returnpath1.borrow_mut();
+            returnpath1.tick();
+            frame.extend(returnpath1.draw(theme));
+
+            let mut returnpath2 = returnpath2.borrow_mut();
+            returnpath2.tick();
+            frame.extend(returnpath2.draw(theme));
+
+            let mut sink = sink.borrow_mut();
+            sink.tick();
+            frame.extend(sink.draw(theme));
+        }
+
+        frame.extend(annotations.draw(tick, theme));
+
+        anim.frames.push(frame);
+    }
+
+    if check::is_enabled() {
+        let mut in_flight = bottleneck.borrow().get_pkts();
+        in_flight.extend(ret_bottleneck.borrow().get_pkts());
+        for (arrival, departure, _, returnpath1, returnpath2, _) in &flows {
+            in_flight.extend(arrival.borrow().get_pkts());
+            in_flight.extend(departure.borrow().get_pkts());
+            in_flight.extend(returnpath1.borrow().get_pkts());
+            in_flight.extend(returnpath2.borrow().get_pkts());
+        }
+        check::finish(in_flight)?;
+    }
+    Ok(config.output.finish(anim))
+}
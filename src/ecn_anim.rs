@@ -0,0 +1,289 @@
+//! Animate window-based flows that react to ECN marks from a shared
+//! bottleneck
+use crate::annotation::{Annotations, Caption};
+use crate::base::{Coord, Element, Packet, DATA_PKT_WIDTH, PKT_HEIGHT};
+use crate::bottleneck::Bottleneck;
+use crate::check;
+use crate::layout::{Layout, Topology};
+use crate::node::{Diagram, NodeKind};
+use crate::output::{Animation, OutputConfig};
+use crate::pool::Pool;
+use crate::router::Router;
+use crate::scene::{Color, Style};
+use crate::sender::{EcnResponse, WindowSender};
+use crate::simple_elems::Acker;
+use crate::transport::{PathShape, Transport};
+use std::cell::RefCell;
+use std::error::Error;
+use std::rc::Rc;
+
+/// Configuration of one window-based flow
+#[derive(Clone)]
+pub struct EcnFlowConfig {
+    /// How the sender reacts to marks
+    pub response: EcnResponse,
+    /// Congestion window (in packets) to start with
+    pub init_cwnd: f64,
+    /// If given, pace packets at this multiple of `cwnd / srtt` rather than
+    /// sending them in bursts
+    pub pacing_gain: Option<f64>,
+    /// Round trip propagation delay, in the units `Transport::new` takes
+    pub rtt: u64,
+    /// `(tick, rtt)` pairs in increasing order of tick. Packets sent from each
+    /// tick on see the new RTT
+    pub rtt_schedule: Vec<(u64, u64)>,
+    /// `(start, end)` ticks during which the flow is routed along a longer path
+    /// to the bottleneck that adds `flow.rtt * 2 / 5` to its RTT
+    pub detour: Option<(u64, u64)>,
+    /// Color of the flow's packets
+    pub color: Color,
+}
+
+#[derive(Clone, Default)]
+pub struct EcnAnimConfig {
+    /// Where to output the .gif file
+    pub filename: String,
+    /// Buffer size of the bottleneck
+    pub bufsize: u64,
+    /// Mark packets that arrive to find at least this many packets queued
+    pub ecn_threshold: Option<u64>,
+    /// Bottleneck link rate
+    pub bottleneck_intersend: u64,
+    /// One entry per flow
+    pub flows: Vec<EcnFlowConfig>,
+    /// Number of ticks to animate
+    pub num_ticks: u64,
+    /// Should we draw the current tick?
+    pub show_clock: bool,
+    /// Text to show between given ticks
+    pub captions: Vec<Caption>,
+    /// Size, frame rate and chart ranges of the output
+    pub output: OutputConfig,
+}
+
+pub fn ecn_anim(config: &EcnAnimConfig, pool: &Pool) -> Result<(), Box<dyn Error>> {
+    ecn_frames(config)?.save_gif(&config.filename, pool)
+}
+
+/// Simulate every tick of the animation
+pub fn ecn_frames(config: &EcnAnimConfig) -> Result<Animation, Box<dyn Error>> {
+    let theme = &config.output.theme;
+    let layout = Layout::new(&Topology {
+        hops: vec![config.bufsize],
+        ret_bufsize: 0,
+        num_flows: config.flows.len(),
+        flow_sep: PKT_HEIGHT * 6.,
+    });
+    let mut anim = Animation::new(config.output.canvas(
+        (1600, layout.canvas_height()),
+        layout.x_range.clone(),
+        layout.y_range.clone(),
+    ));
+
+    // We will populate `next` after constructing the flows
+    let bottleneck = Rc::new(RefCell::new(Bottleneck::<dyn Element>::new(
+        layout.hops[0],
+        config.bufsize,
+        vec![config.bottleneck_intersend],
+        vec![],
+        true,
+    )));
+    bottleneck
+        .borrow_mut()
+        .set_ecn_threshold(config.ecn_threshold);
+
+    // (sender, router, arrival, detour, departure, acker, returnpath) for each
+    // flow. The router at the sender picks between the direct path and the
+    // detour. Acks go straight back to the sender
+    let mut flows = Vec::new();
+    for (i, flow) in config.flows.iter().enumerate() {
+        // Split the RTT the same way for every entry in the schedule
+        let split = |rtt: u64| {
+            let (arrival, departure) = (rtt * 2 / 5, rtt / 5);
+            (arrival, departure, rtt - arrival - departure)
+        };
+        let (arrival, departure, returnpath) = split(flow.rtt);
+        let schedules: Vec<_> = flow
+            .rtt_schedule
+            .iter()
+            .map(|(tick, rtt)| (*tick, split(*rtt)))
+            .collect();
+
+        let mut router = Router::new(layout.senders[i]);
+        let mut direct = Transport::new(arrival, bottleneck.clone());
+        direct.set_delay_schedule(schedules.iter().map(|(t, d)| (*t, d.0)).collect());
+        let direct = Rc::new(RefCell::new(direct));
+        router.add_route(i as u16, direct.clone());
+        let detour = flow.detour.map(|(start, end)| {
+            // Bulge away from the bottleneck so the two paths are distinct
+            let from = layout.senders[i];
+            let to = bottleneck.borrow().get_enqueue_coord();
+            let bulge = if from.1 < to.1 { -1. } else { 1. } * PKT_HEIGHT * 4.;
+            let mut detour = Transport::new(arrival * 2, bottleneck.clone());
+            detour.set_delay_schedule(schedules.iter().map(|(t, d)| (*t, d.0 * 2)).collect());
+            detour.set_shape(PathShape::Bezier(
+                from.add(to.sub(from).mul(1. / 3.)).add(Coord(0., bulge)),
+                from.add(to.sub(from).mul(2. / 3.)).add(Coord(0., bulge)),
+            ));
+            let detour = Rc::new(RefCell::new(detour));
+            router.schedule_route(start, i as u16, detour.clone());
+            router.schedule_route(end, i as u16, direct.clone());
+            detour
+        });
+        let router = Rc::new(RefCell::new(router));
+        let template = Packet::new(
+            DATA_PKT_WIDTH,
+            layout.senders[i],
+            i as u16,
+            i as u16,
+            Style::from(flow.color).filled(),
+        );
+        let mut sender = WindowSender::new(
+            template,
+            layout.sinks[i],
+            router.clone(),
+            flow.response,
+            flow.init_cwnd,
+        );
+        if let Some(gain) = flow.pacing_gain {
+            // `Transport` takes 4 ticks per unit of delay
+            sender.set_pacing(gain, flow.rtt * 4);
+        }
+        let sender = Rc::new(RefCell::new(sender));
+        let mut returnpath = Transport::new(returnpath, sender.clone());
+        returnpath.set_delay_schedule(schedules.iter().map(|(t, d)| (*t, d.2)).collect());
+        let returnpath = Rc::new(RefCell::new(returnpath));
+        let acker = Rc::new(RefCell::new(Acker {
+            rcv_coord: layout.receivers[i].0,
+            snd_coord: layout.receivers[i].1,
+            next: returnpath.clone(),
+        }));
+        let mut departure = Transport::new(departure, acker.clone());
+        departure.set_delay_schedule(schedules.iter().map(|(t, d)| (*t, d.1)).collect());
+        let departure = Rc::new(RefCell::new(departure));
+        flows.push((sender, router, direct, detour, departure, acker, returnpath));
+    }
+
+    bottleneck.borrow_mut().set_next(
+        flows
+            .iter()
+            .map(|f| f.4.clone() as Rc<RefCell<dyn Element>>)
+            .collect(),
+    );
+
+    let mut diagram = Diagram::new();
+    diagram.node(NodeKind::Router, bottleneck.borrow().get_dequeue_coord());
+    for (i, (_, _, arrival, detour, departure, _, returnpath)) in flows.iter().enumerate() {
+        diagram.node(NodeKind::Host, layout.host_coord(i));
+        diagram.node(NodeKind::Receiver, layout.receiver_coord(i));
+        diagram.link(&*arrival.borrow(), layout.senders[i]);
+        if let Some(detour) = detour {
+            diagram.link(&*detour.borrow(), layout.senders[i]);
+        }
+        diagram.link(
+            &*departure.borrow(),
+            bottleneck.borrow().get_dequeue_coord(),
+        );
+        diagram.link(&*returnpath.borrow(), layout.receivers[i].1);
+    }
+
+    let mut annotations = Annotations::new();
+    annotations.label(
+        "Bottleneck",
+        bottleneck.borrow().get_enqueue_coord(),
+        Coord(
+            DATA_PKT_WIDTH * (config.bufsize as f64 / 2. + 1.),
+            PKT_HEIGHT * 1.1,
+        ),
+    );
+    annotations.legend(
+        layout.legend_coord(),
+        config
+            .flows
+            .iter()
+            .map(|f| {
+                let name = match f.response {
+                    EcnResponse::Ignore => "Ignores ECN",
+                    EcnResponse::Classic => "Classic ECN",
+                    EcnResponse::Dctcp { .. } => "DCTCP",
+                };
+                let paced = if f.pacing_gain.is_some() {
+                    ", paced"
+                } else {
+                    ""
+                };
+                (
+                    format!("{}{} (RTT {})", name, paced, f.rtt),
+                    Style::from(f.color).filled(),
+                )
+            })
+            .collect(),
+    );
+    if config.show_clock {
+        annotations.clock(layout.clock_coord());
+    }
+    annotations.captions(layout.caption_coord(), config.captions.clone());
+
+    for tick in 0..config.num_ticks {
+        let mut frame = diagram.draw(theme);
+
+        // Produce packets
+        for (sender, router, arrival, detour, ..) in &flows {
+            let mut router = router.borrow_mut();
+            router.tick();
+            frame.extend(router.draw(theme));
+
+            let mut sender = sender.borrow_mut();
+            sender.tick();
+            frame.extend(sender.draw(theme));
+
+            let mut arrival = arrival.borrow_mut();
+            arrival.tick();
+            frame.extend(arrival.draw(theme));
+
+            if let Some(detour) = detour {
+                let mut detour = detour.borrow_mut();
+                detour.tick();
+                frame.extend(detour.draw(theme));
+            }
+        }
+
+        {
+            let mut bottleneck = bottleneck.borrow_mut();
+            bottleneck.tick();
+            frame.extend(bottleneck.draw(theme));
+        }
+
+        for (.., departure, acker, returnpath) in &flows {
+            let mut departure = departure.borrow_mut();
+            departure.tick();
+            frame.extend(departure.draw(theme));
+
+            let mut acker = acker.borrow_mut();
+            acker.tick();
+            frame.extend(acker.draw(theme));
+
+            let mut returnpath = returnpath.borrow_mut();
+            returnpath.tick();
+            frame.extend(returnpath.draw(theme));
+        }
+
+        frame.extend(annotations.draw(tick, theme));
+
+        anim.frames.push(frame);
+    }
+
+    if check::is_enabled() {
+        let mut in_flight = bottleneck.borrow().get_pkts();
+        for (_, _, arrival, detour, departure, _, returnpath) in &flows {
+            in_flight.extend(arrival.borrow().get_pkts());
+            if let Some(detour) = detour {
+                in_flight.extend(detour.borrow().get_pkts());
+            }
+            in_flight.extend(departure.borrow().get_pkts());
+            in_flight.extend(returnpath.borrow().get_pkts());
+        }
+        check::finish(in_flight)?;
+    }
+    Ok(config.output.finish(anim))
+}
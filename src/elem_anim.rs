@@ -1,13 +1,19 @@
 //! Animate just a single element
+use crate::annotation::{Annotations, Caption};
 use crate::base::{Coord, Element, Packet, DATA_PKT_WIDTH};
 use crate::bottleneck::Bottleneck;
+use crate::check;
+use crate::node::Diagram;
+use crate::output::{Animation, OutputConfig};
+use crate::pool::Pool;
+use crate::scene::{Color, Style};
 use crate::simple_elems::Sink;
 use crate::transport::Transport;
-use plotters::prelude::*;
 use std::cell::RefCell;
 use std::error::Error;
 use std::rc::Rc;
 
+#[derive(Clone, Default)]
 pub struct ElemAnimConfig {
     pub filename: String,
     /// Buffer size for both directions
@@ -18,11 +24,24 @@ pub struct ElemAnimConfig {
     pub sender_intersend: Vec<u64>,
     /// Number of ticks to animate
     pub num_ticks: u64,
+    /// Should we draw the current tick?
+    pub show_clock: bool,
+    /// Text to show between given ticks
+    pub captions: Vec<Caption>,
+    /// Size, frame rate and chart ranges of the output
+    pub output: OutputConfig,
 }
 
-pub fn elem_anim(config: &ElemAnimConfig) -> Result<(), Box<dyn Error>> {
-    let root = BitMapBackend::gif(&config.filename, (300, 100), 16)?.into_drawing_area();
+/// Size of the animation in pixels
+pub const CANVAS_SIZE: (u32, u32) = (300, 100);
 
+pub fn elem_anim(config: &ElemAnimConfig, pool: &Pool) -> Result<(), Box<dyn Error>> {
+    elem_frames(config)?.save_gif(&config.filename, pool)
+}
+
+/// Simulate every tick of the animation
+pub fn elem_frames(config: &ElemAnimConfig) -> Result<Animation, Box<dyn Error>> {
+    let theme = &config.output.theme;
     let sink = Rc::new(RefCell::new(Sink {
         coord: Coord(5., 0.),
     }));
@@ -36,49 +55,65 @@ pub fn elem_anim(config: &ElemAnimConfig) -> Result<(), Box<dyn Error>> {
     )));
     let arrival = Rc::new(RefCell::new(Transport::new(16, elem.clone())));
 
+    let mut diagram = Diagram::new();
+    diagram.link(&*arrival.borrow(), Coord(-5., 0.));
+    diagram.link(&*departure.borrow(), elem.borrow().get_dequeue_coord());
+
+    let mut annotations = Annotations::new();
+    if config.show_clock {
+        annotations.clock(Coord(-4.8, 4.));
+    }
+    annotations.captions(Coord(0., -3.), config.captions.clone());
+
     let mut intersend_index = 0;
     let mut time_since_send = 0;
-    for _tick in 0..config.num_ticks {
-        root.fill(&WHITE)?;
-        let chart = ChartBuilder::on(&root).build_cartesian_2d(-5.0..5.0, -5.0..5.0)?;
+    let mut anim = Animation::new(config.output.canvas(CANVAS_SIZE, -5.0..5.0, -5.0..5.0));
+    for tick in 0..config.num_ticks {
+        let mut frame = diagram.draw(theme);
 
         // Produce packets
         {
             let mut arrival = arrival.borrow_mut();
             if time_since_send >= config.sender_intersend[intersend_index] {
-                arrival.enqueue(&Packet {
-                    size: DATA_PKT_WIDTH,
-                    coord: Coord(-5., 0.),
-                    addr: 0,
-                    style: ShapeStyle::from(RED).filled(),
-                });
+                let mut pkt = Packet::new(
+                    DATA_PKT_WIDTH,
+                    Coord(-5., 0.),
+                    0,
+                    0,
+                    Style::from(Color::RED).filled(),
+                );
+                check::created(&mut pkt);
+                arrival.enqueue(&pkt);
                 intersend_index = (intersend_index + 1) % config.sender_intersend.len();
                 time_since_send = 0;
             }
             time_since_send += 1;
             arrival.tick();
-            for e in arrival.draw() {
-                chart.plotting_area().draw(&e)?;
-            }
+            frame.extend(arrival.draw(theme));
         }
 
         {
             let mut elem = elem.borrow_mut();
             elem.tick();
-            for e in elem.draw() {
-                chart.plotting_area().draw(&e)?;
-            }
+            frame.extend(elem.draw(theme));
         }
 
         {
             let mut departure = departure.borrow_mut();
             departure.tick();
-            for e in departure.draw() {
-                chart.plotting_area().draw(&e)?;
-            }
+            frame.extend(departure.draw(theme));
         }
 
-        root.present()?;
+        frame.extend(annotations.draw(tick, theme));
+
+        anim.frames.push(frame);
+    }
+
+    if check::is_enabled() {
+        let mut in_flight = arrival.borrow().get_pkts();
+        in_flight.extend(elem.borrow().get_pkts());
+        in_flight.extend(departure.borrow().get_pkts());
+        check::finish(in_flight)?;
     }
-    Ok(())
+    Ok(config.output.finish(anim))
 }
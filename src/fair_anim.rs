@@ -1,13 +1,20 @@
 //! Animate two flows fairly sharing a link
+use crate::annotation::{Annotations, Caption};
 use crate::base::{Coord, Element, Packet, DATA_PKT_WIDTH, PKT_HEIGHT};
 use crate::bottleneck::Bottleneck;
+use crate::check;
+use crate::layout::{Layout, Topology};
+use crate::node::{Diagram, NodeKind};
+use crate::output::{Animation, OutputConfig};
+use crate::pool::Pool;
+use crate::scene::{Color, Style};
 use crate::simple_elems::{Acker, Sink};
 use crate::transport::Transport;
-use plotters::prelude::*;
 use std::cell::RefCell;
 use std::error::Error;
 use std::rc::Rc;
 
+#[derive(Clone, Default)]
 pub struct FairAnimConfig {
     /// Where to output the .gif file
     pub filename: String,
@@ -21,25 +28,42 @@ pub struct FairAnimConfig {
     pub extra_packets: u64,
     /// Number of ticks to animate
     pub num_ticks: u64,
+    /// Should we draw the current tick?
+    pub show_clock: bool,
+    /// Text to show between given ticks
+    pub captions: Vec<Caption>,
+    /// Size, frame rate and chart ranges of the output
+    pub output: OutputConfig,
 }
 
-pub fn fair_anim(config: &FairAnimConfig) -> Result<(), Box<dyn Error>> {
-    let root = BitMapBackend::gif(&config.filename, (1600, 400), 16)?.into_drawing_area();
+/// Size of the animation in pixels
+pub const CANVAS_SIZE: (u32, u32) = (1600, 400);
 
-    // Vertical separation between flows
-    let vsep = PKT_HEIGHT * 13.;
+pub fn fair_anim(config: &FairAnimConfig, pool: &Pool) -> Result<(), Box<dyn Error>> {
+    fair_frames(config)?.save_gif(&config.filename, pool)
+}
+
+/// Simulate every tick of the animation
+pub fn fair_frames(config: &FairAnimConfig) -> Result<Animation, Box<dyn Error>> {
+    let theme = &config.output.theme;
+    let layout = Layout::new(&Topology {
+        hops: vec![config.bufsize],
+        ret_bufsize: config.bufsize,
+        num_flows: 2,
+        flow_sep: PKT_HEIGHT * 26.,
+    });
 
     // Shared bottlenecks. We will populate `next` later after we have
     // constructed them
     let ret_bottleneck = Rc::new(RefCell::new(Bottleneck::new(
-        Coord(DATA_PKT_WIDTH * config.bufsize as f64, -PKT_HEIGHT * 2.),
+        layout.ret_hop,
         config.bufsize,
         vec![config.bottleneck_intersend],
         vec![],
         false,
     )));
     let bottleneck = Rc::new(RefCell::new(Bottleneck::new(
-        Coord(0., 0.),
+        layout.hops[0],
         config.bufsize,
         vec![config.bottleneck_intersend],
         vec![],
@@ -47,26 +71,26 @@ pub fn fair_anim(config: &FairAnimConfig) -> Result<(), Box<dyn Error>> {
     )));
 
     let sink_a = Rc::new(RefCell::new(Sink {
-        coord: Coord(-9., -PKT_HEIGHT * 2. + vsep),
+        coord: layout.sinks[0],
     }));
     let returnpath2_a = Rc::new(RefCell::new(Transport::new(16, sink_a.clone())));
     let returnpath1_a = Rc::new(RefCell::new(Transport::new(16, ret_bottleneck.clone())));
     let acker_a = Rc::new(RefCell::new(Acker {
-        rcv_coord: Coord(9., 0. + vsep),
-        snd_coord: Coord(9., -PKT_HEIGHT * 2. + vsep),
+        rcv_coord: layout.receivers[0].0,
+        snd_coord: layout.receivers[0].1,
         next: returnpath1_a.clone(),
     }));
     let departure_a = Rc::new(RefCell::new(Transport::new(16, acker_a.clone())));
     let arrival_a = Rc::new(RefCell::new(Transport::new(32, bottleneck.clone())));
 
     let sink_b = Rc::new(RefCell::new(Sink {
-        coord: Coord(-9., -PKT_HEIGHT * 2. - vsep),
+        coord: layout.sinks[1],
     }));
     let returnpath2_b = Rc::new(RefCell::new(Transport::new(16, sink_b.clone())));
     let returnpath1_b = Rc::new(RefCell::new(Transport::new(16, ret_bottleneck.clone())));
     let acker_b = Rc::new(RefCell::new(Acker {
-        rcv_coord: Coord(9., 0. - vsep),
-        snd_coord: Coord(9., -PKT_HEIGHT * 2. - vsep),
+        rcv_coord: layout.receivers[1].0,
+        snd_coord: layout.receivers[1].1,
         next: returnpath1_b.clone(),
     }));
     let departure_b = Rc::new(RefCell::new(Transport::new(16, acker_b.clone())));
@@ -79,10 +103,59 @@ pub fn fair_anim(config: &FairAnimConfig) -> Result<(), Box<dyn Error>> {
         .borrow_mut()
         .set_next(vec![departure_a.clone(), departure_b.clone()]);
 
+    let mut diagram = Diagram::new();
+    diagram.node(NodeKind::Router, bottleneck.borrow().get_dequeue_coord());
+    diagram.node(
+        NodeKind::Router,
+        ret_bottleneck.borrow().get_dequeue_coord(),
+    );
+    for (i, arrival, departure, returnpath1, returnpath2) in [
+        (0, &arrival_a, &departure_a, &returnpath1_a, &returnpath2_a),
+        (1, &arrival_b, &departure_b, &returnpath1_b, &returnpath2_b),
+    ] {
+        diagram.node(NodeKind::Host, layout.host_coord(i));
+        diagram.node(NodeKind::Receiver, layout.receiver_coord(i));
+        diagram.link(&*arrival.borrow(), layout.senders[i]);
+        diagram.link(
+            &*departure.borrow(),
+            bottleneck.borrow().get_dequeue_coord(),
+        );
+        diagram.link(&*returnpath1.borrow(), layout.receivers[i].1);
+        diagram.link(
+            &*returnpath2.borrow(),
+            ret_bottleneck.borrow().get_dequeue_coord(),
+        );
+    }
+
+    let mut annotations = Annotations::new();
+    annotations.label(
+        "Shared bottleneck",
+        bottleneck.borrow().get_enqueue_coord(),
+        Coord(
+            DATA_PKT_WIDTH * (config.bufsize as f64 / 2. + 1.),
+            PKT_HEIGHT * 1.1,
+        ),
+    );
+    annotations.legend(
+        layout.legend_coord(),
+        vec![
+            (String::from("Flow A"), Style::from(Color::RED).filled()),
+            (String::from("Flow B"), Style::from(Color::GREEN).filled()),
+        ],
+    );
+    if config.show_clock {
+        annotations.clock(layout.clock_coord());
+    }
+    annotations.captions(layout.caption_coord(), config.captions.clone());
+
     let mut num_packets = 0;
+    let mut anim = Animation::new(config.output.canvas(
+        CANVAS_SIZE,
+        layout.x_range.clone(),
+        layout.y_range.clone(),
+    ));
     for tick in 0..config.num_ticks {
-        root.fill(&WHITE)?;
-        let chart = ChartBuilder::on(&root).build_cartesian_2d(-10.0..10.0, -20.0..20.0)?;
+        let mut frame = diagram.draw(theme);
 
         // Produce packets
         {
@@ -91,121 +164,119 @@ pub fn fair_anim(config: &FairAnimConfig) -> Result<(), Box<dyn Error>> {
                 || (num_packets < config.extra_packets
                     && tick % config.sender_intersend.0 == config.sender_intersend.0 / 2)
             {
-                arrival_a.enqueue(&Packet {
-                    size: DATA_PKT_WIDTH,
-                    coord: Coord(-10., vsep),
-                    addr: 0,
-                    style: ShapeStyle::from(RED).filled(),
-                });
+                let mut pkt = Packet::new(
+                    DATA_PKT_WIDTH,
+                    layout.senders[0],
+                    0,
+                    0,
+                    Style::from(Color::RED).filled(),
+                );
+                check::created(&mut pkt);
+                arrival_a.enqueue(&pkt);
                 num_packets += 1;
             }
             arrival_a.tick();
-            for e in arrival_a.draw() {
-                chart.plotting_area().draw(&e)?;
-            }
+            frame.extend(arrival_a.draw(theme));
         }
 
         {
             let mut arrival_b = arrival_b.borrow_mut();
             if tick % config.sender_intersend.1 == config.sender_intersend.1 / 2 {
-                arrival_b.enqueue(&Packet {
-                    size: DATA_PKT_WIDTH,
-                    coord: Coord(-10., -vsep),
-                    addr: 1,
-                    style: ShapeStyle::from(GREEN).filled(),
-                });
+                let mut pkt = Packet::new(
+                    DATA_PKT_WIDTH,
+                    layout.senders[1],
+                    1,
+                    1,
+                    Style::from(Color::GREEN).filled(),
+                );
+                check::created(&mut pkt);
+                arrival_b.enqueue(&pkt);
                 num_packets += 1;
             }
             arrival_b.tick();
-            for e in arrival_b.draw() {
-                chart.plotting_area().draw(&e)?;
-            }
+            frame.extend(arrival_b.draw(theme));
         }
 
         // Shared bottleneck
         {
             let mut bottleneck = bottleneck.borrow_mut();
             bottleneck.tick();
-            for e in bottleneck.draw() {
-                chart.plotting_area().draw(&e)?;
-            }
+            frame.extend(bottleneck.draw(theme));
         }
 
         {
             let mut ret_bottleneck = ret_bottleneck.borrow_mut();
             ret_bottleneck.tick();
-            for e in ret_bottleneck.draw() {
-                chart.plotting_area().draw(&e)?;
-            }
+            frame.extend(ret_bottleneck.draw(theme));
         }
 
         // Flow A
         {
             let mut acker_a = acker_a.borrow_mut();
             acker_a.tick();
-            for e in acker_a.draw() {
-                chart.plotting_area().draw(&e)?;
-            }
+            frame.extend(acker_a.draw(theme));
         }
 
         {
             let mut departure_a = departure_a.borrow_mut();
             departure_a.tick();
-            for e in departure_a.draw() {
-                chart.plotting_area().draw(&e)?;
-            }
+            frame.extend(departure_a.draw(theme));
         }
 
         {
             let mut returnpath1_a = returnpath1_a.borrow_mut();
             returnpath1_a.tick();
-            for e in returnpath1_a.draw() {
-                chart.plotting_area().draw(&e)?;
-            }
+            frame.extend(returnpath1_a.draw(theme));
         }
 
         {
             let mut returnpath2_a = returnpath2_a.borrow_mut();
             returnpath2_a.tick();
-            for e in returnpath2_a.draw() {
-                chart.plotting_area().draw(&e)?;
-            }
+            frame.extend(returnpath2_a.draw(theme));
         }
 
         // Flow B
         {
             let mut departure_b = departure_b.borrow_mut();
             departure_b.tick();
-            for e in departure_b.draw() {
-                chart.plotting_area().draw(&e)?;
-            }
+            frame.extend(departure_b.draw(theme));
         }
 
         {
             let mut acker_b = acker_b.borrow_mut();
             acker_b.tick();
-            for e in acker_b.draw() {
-                chart.plotting_area().draw(&e)?;
-            }
+            frame.extend(acker_b.draw(theme));
         }
 
         {
             let mut returnpath1_b = returnpath1_b.borrow_mut();
             returnpath1_b.tick();
-            for e in returnpath1_b.draw() {
-                chart.plotting_area().draw(&e)?;
-            }
+            frame.extend(returnpath1_b.draw(theme));
         }
 
         {
             let mut returnpath2_b = returnpath2_b.borrow_mut();
             returnpath2_b.tick();
-            for e in returnpath2_b.draw() {
-                chart.plotting_area().draw(&e)?;
-            }
+            frame.extend(returnpath2_b.draw(theme));
         }
 
-        root.present()?;
+        frame.extend(annotations.draw(tick, theme));
+
+        anim.frames.push(frame);
+    }
+
+    if check::is_enabled() {
+        let mut in_flight = arrival_a.borrow().get_pkts();
+        in_flight.extend(bottleneck.borrow().get_pkts());
+        in_flight.extend(ret_bottleneck.borrow().get_pkts());
+        in_flight.extend(arrival_b.borrow().get_pkts());
+        in_flight.extend(departure_a.borrow().get_pkts());
+        in_flight.extend(departure_b.borrow().get_pkts());
+        in_flight.extend(returnpath1_a.borrow().get_pkts());
+        in_flight.extend(returnpath1_b.borrow().get_pkts());
+        in_flight.extend(returnpath2_a.borrow().get_pkts());
+        in_flight.extend(returnpath2_b.borrow().get_pkts());
+        check::finish(in_flight)?;
     }
-    Ok(())
+    Ok(config.output.finish(anim))
 }
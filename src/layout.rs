@@ -0,0 +1,198 @@
+//! Places senders, bottlenecks and receivers automatically so presets don't
+//! have to hand-tune coordinates
+use crate::base::{Coord, DATA_PKT_WIDTH, PKT_HEIGHT};
+use std::ops::Range;
+
+/// Pixels per unit on the y-axis when a preset sizes its canvas to fit the
+/// layout
+pub const PIXELS_PER_UNIT: f64 = 10.;
+/// Minimum distance (in chart units) between a bottleneck and its neighbors
+const MIN_HOP_GAP: f64 = 3.;
+/// Space left around the outermost rows
+const MARGIN: f64 = 2.;
+
+/// Description of the network we want to lay out. All flows go through every
+/// hop and share a single bottleneck on the return path
+pub struct Topology {
+    /// Buffer size (in packets) of each bottleneck on the forward path, in order
+    pub hops: Vec<u64>,
+    /// Buffer size of the bottleneck on the return path
+    pub ret_bufsize: u64,
+    pub num_flows: usize,
+    /// Vertical distance between adjacent flows
+    pub flow_sep: f64,
+}
+
+pub struct Layout {
+    pub x_range: Range<f64>,
+    pub y_range: Range<f64>,
+    /// Where each flow's data packets start
+    pub senders: Vec<Coord>,
+    /// Where each flow's acks end
+    pub sinks: Vec<Coord>,
+    /// (coord where data arrives, coord where the ack leaves) for each flow's
+    /// receiver
+    pub receivers: Vec<(Coord, Coord)>,
+    /// Coord of each bottleneck on the forward path
+    pub hops: Vec<Coord>,
+    /// Coord of the bottleneck on the return path. It sits under the first hop
+    pub ret_hop: Coord,
+}
+
+impl Layout {
+    pub fn new(topology: &Topology) -> Self {
+        let num_hops = topology.hops.len();
+        let widest = topology.hops.iter().copied().max().unwrap_or(0) as f64 * DATA_PKT_WIDTH;
+        let width = f64::max(20., (num_hops + 1) as f64 * (widest + MIN_HOP_GAP));
+        let (x_min, x_max) = (-width / 2., width / 2.);
+
+        // Flows are spread symmetrically about the bottlenecks
+        let flow_y =
+            |i: usize| topology.flow_sep * ((topology.num_flows as f64 - 1.) / 2. - i as f64);
+        let ack_offset = Coord(0., -PKT_HEIGHT * 2.);
+
+        let hops: Vec<Coord> = (0..num_hops)
+            .map(|i| Coord(x_min + (i + 1) as f64 * width / (num_hops + 1) as f64, 0.))
+            .collect();
+        let ret_hop = hops
+            .first()
+            .copied()
+            .unwrap_or(Coord(0., 0.))
+            .add(Coord(topology.ret_bufsize as f64 * DATA_PKT_WIDTH, 0.))
+            .add(ack_offset);
+
+        let mut senders = Vec::new();
+        let mut sinks = Vec::new();
+        let mut receivers = Vec::new();
+        for i in 0..topology.num_flows {
+            let y = flow_y(i);
+            senders.push(Coord(x_min, y));
+            sinks.push(Coord(x_min + 1., y).add(ack_offset));
+            receivers.push((Coord(x_max - 1., y), Coord(x_max - 1., y).add(ack_offset)));
+        }
+
+        // Keep the chart centered vertically on the bottlenecks so they stay
+        // in the middle of the frame
+        let lowest = f64::min(
+            ret_hop.1,
+            flow_y(topology.num_flows.max(1) - 1) + ack_offset.1,
+        );
+        let highest = f64::max(0., flow_y(0));
+        let half_height = f64::max(5., f64::max(-lowest, highest) + MARGIN);
+
+        Self {
+            x_range: x_min..x_max,
+            y_range: -half_height..half_height,
+            senders,
+            sinks,
+            receivers,
+            hops,
+            ret_hop,
+        }
+    }
+
+    /// Center of the icon for the host that runs flow `i`
+    pub fn host_coord(&self, i: usize) -> Coord {
+        Coord(self.x_range.start + 0.5, self.senders[i].1 - PKT_HEIGHT)
+    }
+
+    /// Center of the icon for flow `i`'s receiver
+    pub fn receiver_coord(&self, i: usize) -> Coord {
+        Coord(self.x_range.end - 0.6, self.receivers[i].0 .1 - PKT_HEIGHT)
+    }
+
+    /// Height in pixels of a canvas that shows `y_range` at `PIXELS_PER_UNIT`
+    pub fn canvas_height(&self) -> u32 {
+        ((self.y_range.end - self.y_range.start) * PIXELS_PER_UNIT) as u32
+    }
+
+    /// Where to put the legend: top left, just right of the clock
+    pub fn legend_coord(&self) -> Coord {
+        Coord(self.x_range.start + 1.5, self.y_range.end - 1.)
+    }
+
+    /// Where to put the clock: the top-left corner
+    pub fn clock_coord(&self) -> Coord {
+        Coord(self.x_range.start + 0.2, self.y_range.end - 1.)
+    }
+
+    /// Where to put captions: top center
+    pub fn caption_coord(&self) -> Coord {
+        Coord(
+            (self.x_range.start + self.x_range.end) / 2.,
+            self.y_range.end - 1.,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: Coord, expected: Coord) {
+        assert!(
+            actual.sub(expected).norm() < 0.01,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    fn one_flow(hops: Vec<u64>) -> Layout {
+        Layout::new(&Topology {
+            hops,
+            ret_bufsize: 8,
+            num_flows: 1,
+            flow_sep: 0.,
+        })
+    }
+
+    #[test]
+    fn single_hop_matches_path_anim() {
+        // The coordinates the path animations used before they were laid out
+        // automatically
+        let layout = one_flow(vec![8]);
+        assert_eq!(layout.x_range, -10.0..10.0);
+        assert_eq!(layout.y_range, -5.0..5.0);
+        assert_near(layout.hops[0], Coord(0., 0.));
+        assert_near(layout.ret_hop, Coord(2., -PKT_HEIGHT * 2.));
+        assert_near(layout.senders[0], Coord(-10., 0.));
+        assert_near(layout.sinks[0], Coord(-9., -PKT_HEIGHT * 2.));
+        assert_near(layout.receivers[0].0, Coord(9., 0.));
+        assert_near(layout.receivers[0].1, Coord(9., -PKT_HEIGHT * 2.));
+        assert_near(layout.host_coord(0), Coord(-9.5, -PKT_HEIGHT));
+        assert_near(layout.receiver_coord(0), Coord(9.4, -PKT_HEIGHT));
+        assert_near(layout.clock_coord(), Coord(-9.8, 4.));
+        assert_near(layout.caption_coord(), Coord(0., 4.));
+        assert_eq!(layout.canvas_height(), 100);
+    }
+
+    #[test]
+    fn two_hops_match_two_bottlenecks_anim() {
+        let layout = one_flow(vec![8, 4]);
+        assert_eq!(layout.x_range, -10.0..10.0);
+        assert_near(layout.hops[0], Coord(-3.33, 0.));
+        assert_near(layout.hops[1], Coord(3.33, 0.));
+        // The return path bottleneck sits under the first hop
+        assert_near(layout.ret_hop, Coord(-1.33, -PKT_HEIGHT * 2.));
+    }
+
+    #[test]
+    fn spreads_flows_evenly() {
+        let layout = Layout::new(&Topology {
+            hops: vec![16],
+            ret_bufsize: 0,
+            num_flows: 3,
+            flow_sep: 6.,
+        });
+        let ys: Vec<f64> = layout.senders.iter().map(|c| c.1).collect();
+        assert_eq!(ys, vec![6., 0., -6.]);
+        for (i, y) in ys.iter().enumerate() {
+            assert_eq!(layout.receivers[i].0 .1, *y);
+            assert_eq!(layout.sinks[i].1, y - PKT_HEIGHT * 2.);
+        }
+        // Centered on the bottleneck, with room for the lowest flow's acks
+        assert_eq!(layout.y_range, -11.0..11.0);
+        assert_eq!(layout.canvas_height(), 220);
+    }
+}
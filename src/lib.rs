@@ -0,0 +1,30 @@
+pub mod ack_path;
+pub mod annotation;
+pub mod base;
+pub mod bottleneck;
+pub mod check;
+pub mod dumbbell_anim;
+pub mod ecn_anim;
+pub mod elem_anim;
+pub mod fair_anim;
+pub mod layout;
+pub mod node;
+pub mod output;
+pub mod parking_lot_anim;
+pub mod path_anim;
+pub mod pool;
+pub mod presets;
+pub mod router;
+pub mod scenario;
+pub mod scene;
+pub mod sender;
+pub mod simple_elems;
+pub mod simulation;
+pub mod starvation_anim;
+pub mod sweep;
+pub mod theme;
+pub mod traffic;
+pub mod transport;
+pub mod two_bottlenecks_anim;
+pub mod wifi;
+pub mod wifi_anim;
@@ -1,150 +1,120 @@
-mod base;
-mod bottleneck;
-mod elem_anim;
-mod fair_anim;
-mod path_anim;
-mod simple_elems;
-mod starvation_anim;
-mod transport;
-mod two_bottlenecks_anim;
-
-use crate::elem_anim::{elem_anim, ElemAnimConfig};
-use crate::fair_anim::{fair_anim, FairAnimConfig};
-use crate::path_anim::{path_anim, PathAnimConfig};
-use crate::starvation_anim::starvation_anim;
-use crate::two_bottlenecks_anim::{two_bottlenecks_anim, TwoBottlenecksAnimConfig};
+use anim::check;
+use anim::dumbbell_anim::dumbbell_anim;
+use anim::ecn_anim::ecn_anim;
+use anim::elem_anim::elem_anim;
+use anim::fair_anim::fair_anim;
+use anim::output::OutputConfig;
+use anim::parking_lot_anim::parking_lot_anim;
+use anim::path_anim::path_anim;
+use anim::pool::{Job, Pool};
+use anim::presets;
+use anim::scenario::DctcpScenario;
+use anim::starvation_anim::starvation_anim;
+use anim::sweep::sweep_anim;
+use anim::theme::Theme;
+use anim::two_bottlenecks_anim::two_bottlenecks_anim;
+use anim::wifi_anim::wifi_anim;
+use std::env;
 use std::error::Error;
 
 fn main() -> Result<(), Box<dyn Error>> {
-    starvation_anim()?;
-
-    let two_bottlenecks_config = TwoBottlenecksAnimConfig {
-        filename: String::from("two-bottlenecks-copa.gif"),
-        bufsize1: 8,
-        bottleneck1_intersend: vec![73, 1, 1, 1, 1, 1, 1, 1],
-        bufsize2: 8,
-        bottleneck2_intersend: vec![10],
-        sender_intersend: 15,
-        num_extra_packets: 0,
-        num_ticks: 640,
-        draw_buffer: (true, true),
+    // Restyle every animation, e.g. ANIM_THEME=dark
+    let theme = match env::var("ANIM_THEME") {
+        Ok(name) => Theme::by_name(&name).ok_or(format!("unknown theme {}", name))?,
+        Err(_) => Theme::default(),
     };
-    two_bottlenecks_anim(&two_bottlenecks_config)?;
-
-    let two_bottlenecks_config = TwoBottlenecksAnimConfig {
-        filename: String::from("two-bottlenecks-ideal.gif"),
-        bufsize1: 8,
-        bottleneck1_intersend: vec![10],
-        bufsize2: 4,
-        bottleneck2_intersend: vec![10],
-        sender_intersend: 10,
-        num_extra_packets: 7,
-        num_ticks: 640,
-        draw_buffer: (true, false),
+    let output = OutputConfig {
+        theme: theme.clone(),
+        ..Default::default()
     };
-    two_bottlenecks_anim(&two_bottlenecks_config)?;
 
-    let adversary_config = TwoBottlenecksAnimConfig {
-        filename: String::from("two-bottlenecks-adversary.gif"),
-        bufsize1: 8,
-        bottleneck1_intersend: vec![10],
-        bufsize2: 4,
-        //bottleneck2_intersend: vec![5, 0, 0, 20, 10, 0, 30, 15],
-        bottleneck2_intersend: vec![0, 0, 30],
-        sender_intersend: 10,
-        num_extra_packets: 7,
-        num_ticks: 640,
-        draw_buffer: (true, false),
-    };
-    two_bottlenecks_anim(&adversary_config)?;
+    let starvation_output = output.clone();
+    let mut rtt_config = presets::rtt_sweep();
+    rtt_config.output.theme = theme.clone();
+    let mut bufsize_config = presets::bufsize_sweep();
+    bufsize_config.output.theme = theme.clone();
+    let mut jobs: Vec<Job> = vec![
+        Box::new(move |pool| starvation_anim(&starvation_output, pool)),
+        Box::new(move |pool| {
+            let scenario = |rtt: f64| {
+                DctcpScenario {
+                    rtt: rtt.round() as u64,
+                    ..Default::default()
+                }
+                .queue()
+            };
+            sweep_anim(&rtt_config, scenario, pool)
+        }),
+        Box::new(move |pool| {
+            let scenario = |bufsize: f64| {
+                DctcpScenario {
+                    bufsize: bufsize.round() as u64,
+                    rtt: 10,
+                    ..Default::default()
+                }
+                .queue()
+            };
+            sweep_anim(&bufsize_config, scenario, pool)
+        }),
+    ];
 
-    let elem_config = ElemAnimConfig {
-        filename: String::from("elem-ideal.gif"),
-        bufsize: 8,
-        bottleneck_intersend: vec![10],
-        sender_intersend: vec![10],
-        num_ticks: 640,
-    };
-    elem_anim(&elem_config)?;
+    for mut config in presets::two_bottlenecks() {
+        config.output.theme = theme.clone();
+        jobs.push(Box::new(move |pool| two_bottlenecks_anim(&config, pool)));
+    }
 
-    let elem_config = ElemAnimConfig {
-        filename: String::from("elem-agg.gif"),
-        bufsize: 8,
-        bottleneck_intersend: vec![70, 1, 1, 1, 1, 1, 1],
-        sender_intersend: vec![11],
-        num_ticks: 640,
-    };
-    elem_anim(&elem_config)?;
+    for mut config in presets::elem() {
+        config.output.theme = theme.clone();
+        jobs.push(Box::new(move |pool| elem_anim(&config, pool)));
+    }
 
-    let elem_config = ElemAnimConfig {
-        filename: String::from("elem-random.gif"),
-        bufsize: 10,
-        //bottleneck_intersend: vec![10, 5, 2, 15, 20, 2, 12, 12, 12, 5],
-        bottleneck_intersend: vec![5, 1, 1, 1, 1, 12, 20, 1, 10, 6, 6],
-        sender_intersend: vec![6],
-        num_ticks: 640,
-    };
-    elem_anim(&elem_config)?;
+    for mut config in presets::fair() {
+        config.output.theme = theme.clone();
+        jobs.push(Box::new(move |pool| fair_anim(&config, pool)));
+    }
 
-    let elem_config = ElemAnimConfig {
-        filename: String::from("elem-tbf.gif"),
-        bufsize: 10,
-        //bottleneck_intersend: vec![10, 5, 2, 15, 20, 2, 12, 12, 12, 5],
-        bottleneck_intersend: vec![1, 1, 1, 10, 10, 10, 10, 10, 10],
-        sender_intersend: vec![1, 1, 1, 1, 1, 1, 1, 1, 90],
-        num_ticks: 640,
-    };
-    elem_anim(&elem_config)?;
+    for mut config in presets::path() {
+        config.output.theme = theme.clone();
+        jobs.push(Box::new(move |pool| path_anim(&config, pool)));
+    }
 
-    let fair_config = FairAnimConfig {
-        filename: String::from("fair.gif"),
-        bufsize: 8,
-        bottleneck_intersend: 5,
-        sender_intersend: (10, 10),
-        extra_packets: 8,
-        num_ticks: 640,
-    };
-    fair_anim(&fair_config)?;
+    for mut config in presets::dumbbell() {
+        config.output.theme = theme.clone();
+        jobs.push(Box::new(move |pool| dumbbell_anim(&config, pool)));
+    }
 
-    let fair_config = FairAnimConfig {
-        filename: String::from("unfair.gif"),
-        bufsize: 8,
-        bottleneck_intersend: 10,
-        sender_intersend: (11, 110),
-        extra_packets: 8,
-        num_ticks: 640,
-    };
-    fair_anim(&fair_config)?;
+    for mut config in presets::parking_lot() {
+        config.output.theme = theme.clone();
+        jobs.push(Box::new(move |pool| parking_lot_anim(&config, pool)));
+    }
 
-    let path_config = PathAnimConfig {
-        filename: String::from("ideal-slow.gif"),
-        bufsize: 8,
-        bottleneck_intersend: 10,
-        sender_intersend: 20,
-        num_extra_packets: 0,
-        num_ticks: 640,
-    };
-    path_anim(&path_config)?;
+    for mut config in presets::ecn() {
+        config.output.theme = theme.clone();
+        jobs.push(Box::new(move |pool| ecn_anim(&config, pool)));
+    }
 
-    let path_config = PathAnimConfig {
-        filename: String::from("ideal-correct.gif"),
-        bufsize: 8,
-        bottleneck_intersend: 10,
-        sender_intersend: 10,
-        num_extra_packets: 4,
-        num_ticks: 640,
-    };
-    path_anim(&path_config)?;
+    for mut config in presets::wifi() {
+        config.output.theme = theme.clone();
+        jobs.push(Box::new(move |pool| wifi_anim(&config, pool)));
+    }
 
-    let path_config = PathAnimConfig {
-        filename: String::from("ideal-fast.gif"),
-        bufsize: 8,
-        bottleneck_intersend: 10,
-        sender_intersend: 5,
-        num_extra_packets: 0,
-        num_ticks: 640,
-    };
-    path_anim(&path_config)?;
+    run(jobs)
+}
 
-    Ok(())
+/// Run the jobs on a pool of threads and report every one that failed
+fn run(jobs: Vec<Job>) -> Result<(), Box<dyn Error>> {
+    // Follow every packet and fail if any animation loses or duplicates one
+    let checking = env::var_os("ANIM_CHECK").is_some();
+    let jobs = jobs
+        .into_iter()
+        .map(|job| -> Job {
+            Box::new(move |pool| {
+                if checking {
+                    check::enable();
+                }
+                job(pool)
+            })
+        })
+        .collect();
+    Pool::new().run(jobs)
 }
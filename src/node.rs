@@ -0,0 +1,136 @@
+//! Static parts of the network diagram: host, router and receiver icons and
+//! the links packets travel along. These are drawn underneath the packets.
+use crate::base::{Coord, Element};
+use crate::scene::{Shape, Style};
+use crate::theme::Theme;
+use crate::transport::Transport;
+
+/// Half the width and height (in pixels) of host and receiver boxes
+const BOX_SIZE: (i32, i32) = (12, 24);
+/// Radius (in pixels) of router icons
+const ROUTER_RADIUS: i32 = 10;
+
+pub enum NodeKind {
+    /// Where packets originate
+    Host,
+    /// Forwards packets. Typically drawn where a bottleneck's buffer drains
+    Router,
+    /// Where packets are consumed (and possibly acked)
+    Receiver,
+}
+
+pub struct Node {
+    pub kind: NodeKind,
+    /// Center of the icon
+    pub coord: Coord,
+}
+
+impl Node {
+    pub fn draw(&self, theme: &Theme) -> Vec<Shape> {
+        let (w, h) = BOX_SIZE;
+        let r = ROUTER_RADIUS;
+        let at = self.coord;
+        let outline = Style::from(theme.foreground).stroke_width(2);
+        let fill = match self.kind {
+            NodeKind::Host => theme.host,
+            NodeKind::Receiver => theme.receiver,
+            NodeKind::Router => {
+                return vec![
+                    Shape::Circle {
+                        center: at,
+                        radius: r,
+                        style: theme.host.filled(),
+                    },
+                    Shape::Circle {
+                        center: at,
+                        radius: r,
+                        style: outline,
+                    },
+                    // The customary cross on router icons
+                    Shape::IconPath {
+                        at,
+                        points: vec![(-r / 2, -r / 2), (r / 2, r / 2)],
+                        style: Style::from(theme.foreground),
+                    },
+                    Shape::IconPath {
+                        at,
+                        points: vec![(-r / 2, r / 2), (r / 2, -r / 2)],
+                        style: Style::from(theme.foreground),
+                    },
+                ];
+            }
+        };
+        // Hosts and receivers are boxes with a screen, so they look like
+        // computers
+        let screen = ((-w + 4, -h + 4), (w - 4, 0));
+        vec![
+            Shape::IconRect {
+                at,
+                corners: ((-w, -h), (w, h)),
+                style: fill.filled(),
+            },
+            Shape::IconRect {
+                at,
+                corners: ((-w, -h), (w, h)),
+                style: outline,
+            },
+            Shape::IconRect {
+                at,
+                corners: screen,
+                style: theme.background.filled(),
+            },
+            Shape::IconRect {
+                at,
+                corners: screen,
+                style: Style::from(theme.foreground),
+            },
+        ]
+    }
+}
+
+/// Everything that stays put during the animation. Draw this before any of
+/// the elements so packets appear on top
+pub struct Diagram {
+    nodes: Vec<Node>,
+    /// Each link is a polyline
+    links: Vec<Vec<Coord>>,
+}
+
+impl Diagram {
+    pub fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            links: Vec::new(),
+        }
+    }
+
+    pub fn node(&mut self, kind: NodeKind, coord: Coord) {
+        self.nodes.push(Node { kind, coord });
+    }
+
+    /// Draw a line along the route taken by packets that enter `transport`
+    /// at `from`
+    pub fn link<N: Element + ?Sized>(&mut self, transport: &Transport<N>, from: Coord) {
+        self.links.push(transport.link_path(from));
+    }
+
+    pub fn draw(&self, theme: &Theme) -> Vec<Shape> {
+        let mut res = Vec::new();
+        for link in &self.links {
+            res.push(Shape::Path {
+                points: link.clone(),
+                style: Style::from(theme.link).stroke_width(2),
+            });
+        }
+        for node in &self.nodes {
+            res.extend(node.draw(theme));
+        }
+        res
+    }
+}
+
+impl Default for Diagram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
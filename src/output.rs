@@ -0,0 +1,304 @@
+//! Turns simulated frames into pixels. Animations simulate every tick first,
+//! and frames are then rasterized on all cores and encoded in order
+use crate::base::Coord;
+use crate::pool::Pool;
+use crate::scene::{render, Color, Shape};
+use crate::theme::Theme;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::error::Error;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Space left around the scene when fitting to it, in pixels
+const FIT_MARGIN: f64 = 4.;
+
+/// How an animation is turned into pixels. Every animation config has one;
+/// the defaults reproduce the published animations
+#[derive(Clone, Debug)]
+pub struct OutputConfig {
+    /// Size in pixels. `None` lets the animation pick
+    pub size: Option<(u32, u32)>,
+    /// Delay between frames, in milliseconds
+    pub frame_delay: u32,
+    /// Chart coordinates to show. `None` lets the animation pick
+    pub x_range: Option<Range<f64>>,
+    pub y_range: Option<Range<f64>>,
+    /// Shrink or grow the chart ranges to just contain everything drawn. The
+    /// size changes to match unless it was given
+    pub auto_fit: bool,
+    /// Multiplies the size and everything drawn in pixels, for high-DPI output
+    pub scale: f64,
+    pub theme: Theme,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            size: None,
+            frame_delay: 16,
+            x_range: None,
+            y_range: None,
+            auto_fit: false,
+            scale: 1.,
+            theme: Theme::default(),
+        }
+    }
+}
+
+impl OutputConfig {
+    /// The canvas to draw on, given what the animation would pick itself.
+    /// Panics unless `scale` is finite and positive
+    pub fn canvas(&self, size: (u32, u32), x_range: Range<f64>, y_range: Range<f64>) -> Canvas {
+        assert!(
+            self.scale > 0. && self.scale.is_finite(),
+            "scale must be positive, not {}",
+            self.scale
+        );
+        Canvas {
+            size: self.size.unwrap_or(size),
+            x_range: self.x_range.clone().unwrap_or(x_range),
+            y_range: self.y_range.clone().unwrap_or(y_range),
+            frame_delay: self.frame_delay,
+            scale: self.scale,
+            background: self.theme.background,
+        }
+    }
+
+    /// Apply the options that need the simulated frames
+    pub fn finish(&self, mut anim: Animation) -> Animation {
+        if self.auto_fit {
+            anim.fit(self.size.is_none());
+        }
+        anim
+    }
+}
+
+/// Size of an animation and the chart coordinates it shows
+#[derive(Clone, Debug)]
+pub struct Canvas {
+    /// Before scaling
+    pub size: (u32, u32),
+    pub x_range: Range<f64>,
+    pub y_range: Range<f64>,
+    /// Delay between frames, in milliseconds
+    pub frame_delay: u32,
+    pub scale: f64,
+    pub background: Color,
+}
+
+impl Canvas {
+    /// Size of the output in pixels
+    pub fn pixel_size(&self) -> (u32, u32) {
+        (
+            (self.size.0 as f64 * self.scale).round() as u32,
+            (self.size.1 as f64 * self.scale).round() as u32,
+        )
+    }
+
+    /// Chart units covered by one unscaled pixel along each axis
+    fn units_per_px(&self) -> (f64, f64) {
+        (
+            (self.x_range.end - self.x_range.start) / self.size.0 as f64,
+            (self.y_range.end - self.y_range.start) / self.size.1 as f64,
+        )
+    }
+}
+
+/// A simulated animation, ready to be rasterized
+pub struct Animation {
+    pub canvas: Canvas,
+    /// What to draw on each tick
+    pub frames: Vec<Vec<Shape>>,
+}
+
+impl Animation {
+    pub fn new(canvas: Canvas) -> Self {
+        Self {
+            canvas,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Set the chart ranges to a box around every shape of every frame, plus
+    /// a margin of `FIT_MARGIN` pixels. If `resize`, the size changes so that
+    /// shapes keep their size in pixels. Otherwise the box is stretched to the
+    /// size, and the margin is measured in the stretched pixels
+    pub fn fit(&mut self, resize: bool) {
+        let units_per_px = self.canvas.units_per_px();
+        let mut lo = Coord(f64::INFINITY, f64::INFINITY);
+        let mut hi = Coord(f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for shape in self.frames.iter().flatten() {
+            let (l, h) = shape.bounds(units_per_px);
+            lo = Coord(lo.0.min(l.0), lo.1.min(l.1));
+            hi = Coord(hi.0.max(h.0), hi.1.max(h.1));
+        }
+        if lo.0 > hi.0 || lo.1 > hi.1 {
+            // Nothing was drawn
+            return;
+        }
+        let margin = if resize {
+            (FIT_MARGIN * units_per_px.0, FIT_MARGIN * units_per_px.1)
+        } else {
+            // Solve margin = FIT_MARGIN * (span + 2 * margin) / size, so the
+            // margin takes FIT_MARGIN of the pixels once the ranges are set
+            let margin =
+                |span: f64, size: u32| FIT_MARGIN * span / (size as f64 - 2. * FIT_MARGIN).max(1.);
+            (
+                margin(hi.0 - lo.0, self.canvas.size.0),
+                margin(hi.1 - lo.1, self.canvas.size.1),
+            )
+        };
+        self.canvas.x_range = lo.0 - margin.0..hi.0 + margin.0;
+        self.canvas.y_range = lo.1 - margin.1..hi.1 + margin.1;
+        if resize {
+            let x_len = self.canvas.x_range.end - self.canvas.x_range.start;
+            let y_len = self.canvas.y_range.end - self.canvas.y_range.start;
+            self.canvas.size = (
+                (x_len / units_per_px.0).round() as u32,
+                (y_len / units_per_px.1).round() as u32,
+            );
+        }
+    }
+
+    /// Draw frame `tick` onto `root`
+    pub fn draw_frame<DB: DrawingBackend>(
+        &self,
+        tick: usize,
+        root: &DrawingArea<DB, Shift>,
+    ) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        root.fill(&RGBColor::from(self.canvas.background))?;
+        let chart = ChartBuilder::on(root)
+            .build_cartesian_2d(self.canvas.x_range.clone(), self.canvas.y_range.clone())?;
+        render(chart.plotting_area(), &self.frames[tick], self.canvas.scale)?;
+        Ok(())
+    }
+
+    /// Frame `tick` as RGB pixels, row by row
+    pub fn rasterize(&self, tick: usize) -> Result<Vec<u8>, Box<dyn Error>> {
+        let size = self.canvas.pixel_size();
+        let mut buf = vec![0; size.0 as usize * size.1 as usize * 3];
+        {
+            let root = BitMapBackend::with_buffer(&mut buf, size).into_drawing_area();
+            self.draw_frame(tick, &root)?;
+            root.present()?;
+        }
+        Ok(buf)
+    }
+
+    /// Write the first frame to a PNG, for stills
+    pub fn save_png(&self, filename: &str) -> Result<(), Box<dyn Error>> {
+        let root = BitMapBackend::new(filename, self.canvas.pixel_size()).into_drawing_area();
+        self.draw_frame(0, &root)?;
+        root.present()?;
+        Ok(())
+    }
+
+    /// Write every frame to a GIF. Frames are rasterized on the pool, a few
+    /// ahead of the one being written, which bounds how many we hold in memory
+    pub fn save_gif(self, filename: &str, pool: &Pool) -> Result<(), Box<dyn Error>> {
+        let size = self.canvas.pixel_size();
+        let mut gif = BitMapBackend::gif(filename, size, self.canvas.frame_delay)?;
+        let num_frames = self.frames.len();
+        let anim = Arc::new(self);
+        pool.ordered(
+            num_frames,
+            move |tick| anim.rasterize(tick).map_err(|e| e.to_string()),
+            |buf| {
+                gif.blit_bitmap((0, 0), size, &buf?)?;
+                gif.present()?;
+                Ok(())
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::Style;
+
+    fn rect_anim() -> Animation {
+        let mut anim =
+            Animation::new(OutputConfig::default().canvas((100, 100), -10.0..10.0, -10.0..10.0));
+        anim.frames.push(vec![Shape::Rect {
+            corners: (Coord(0., 0.), Coord(10., 5.)),
+            style: Style::from(Color::BLACK),
+        }]);
+        anim
+    }
+
+    #[test]
+    fn fit_keeps_pixels_per_unit() {
+        let mut anim = rect_anim();
+        anim.fit(true);
+        // 0.2 units per pixel, so the margin is 0.8 units
+        let canvas = &anim.canvas;
+        assert!((canvas.x_range.start + 0.8).abs() < 1e-9);
+        assert!((canvas.x_range.end - 10.8).abs() < 1e-9);
+        assert!((canvas.y_range.start + 0.8).abs() < 1e-9);
+        assert!((canvas.y_range.end - 5.8).abs() < 1e-9);
+        assert_eq!(canvas.size, (58, 33));
+
+        let mut anim = rect_anim();
+        anim.fit(false);
+        assert_eq!(anim.canvas.size, (100, 100));
+    }
+
+    #[test]
+    fn fit_without_resize_keeps_margin_in_pixels() {
+        let mut anim = rect_anim();
+        anim.fit(false);
+        // The 10x5 box is stretched to 100 pixels each way, and FIT_MARGIN of
+        // those are left on each side
+        let units_per_px = anim.canvas.units_per_px();
+        let canvas = &anim.canvas;
+        assert!((-canvas.x_range.start / units_per_px.0 - FIT_MARGIN).abs() < 1e-9);
+        assert!(((canvas.x_range.end - 10.) / units_per_px.0 - FIT_MARGIN).abs() < 1e-9);
+        assert!((-canvas.y_range.start / units_per_px.1 - FIT_MARGIN).abs() < 1e-9);
+        assert!(((canvas.y_range.end - 5.) / units_per_px.1 - FIT_MARGIN).abs() < 1e-9);
+
+        // The same holds at a higher scale, in scaled pixels
+        let mut anim = rect_anim();
+        anim.canvas.scale = 2.;
+        anim.fit(false);
+        let px = anim.canvas.pixel_size().0 as f64;
+        let x_len = anim.canvas.x_range.end - anim.canvas.x_range.start;
+        assert!((-anim.canvas.x_range.start / x_len * px - 2. * FIT_MARGIN).abs() < 1e-9);
+    }
+
+    #[test]
+    #[should_panic(expected = "scale must be positive")]
+    fn rejects_zero_scale() {
+        let config = OutputConfig {
+            scale: 0.,
+            ..Default::default()
+        };
+        config.canvas((100, 50), -10.0..10.0, -10.0..10.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "scale must be positive")]
+    fn rejects_negative_scale() {
+        let config = OutputConfig {
+            scale: -1.,
+            ..Default::default()
+        };
+        config.canvas((100, 50), -10.0..10.0, -10.0..10.0);
+    }
+
+    #[test]
+    fn scale_multiplies_pixels() {
+        let config = OutputConfig {
+            scale: 2.,
+            ..Default::default()
+        };
+        let mut anim = rect_anim();
+        anim.canvas = config.canvas((100, 50), -10.0..10.0, -10.0..10.0);
+        assert_eq!(anim.canvas.pixel_size(), (200, 100));
+        assert_eq!(anim.rasterize(0).unwrap().len(), 200 * 100 * 3);
+    }
+}
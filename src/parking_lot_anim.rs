@@ -0,0 +1,346 @@
+//! Animate a parking lot: one long flow crosses a chain of bottlenecks and
+//! each bottleneck also carries a cross flow that enters and leaves there
+use crate::annotation::{Annotations, Caption};
+use crate::base::{Coord, Element, Packet, DATA_PKT_WIDTH, PKT_HEIGHT};
+use crate::bottleneck::Bottleneck;
+use crate::check;
+use crate::dumbbell_anim::FlowConfig;
+use crate::layout::{Layout, Topology};
+use crate::node::{Diagram, NodeKind};
+use crate::output::{Animation, OutputConfig};
+use crate::pool::Pool;
+use crate::router::Router;
+use crate::scene::Style;
+use crate::simple_elems::Acker;
+use crate::transport::Transport;
+use std::cell::RefCell;
+use std::error::Error;
+use std::rc::Rc;
+
+/// Height of the row where cross flows start and end
+const CROSS_Y: f64 = PKT_HEIGHT * 4.;
+/// Horizontal distance between a bottleneck and its cross flow's endpoints
+const CROSS_DX: f64 = 0.9;
+
+#[derive(Clone, Default)]
+pub struct ParkingLotAnimConfig {
+    /// Where to output the .gif file
+    pub filename: String,
+    /// Buffer size of every bottleneck
+    pub bufsize: u64,
+    /// Link rate of each bottleneck, in order. Its length is the number of
+    /// bottlenecks
+    pub bottleneck_intersend: Vec<u64>,
+    /// The long flow
+    pub long_flow: FlowConfig,
+    /// One cross flow per bottleneck
+    pub cross_flows: Vec<FlowConfig>,
+    /// Number of ticks to animate
+    pub num_ticks: u64,
+    /// Should we draw the current tick?
+    pub show_clock: bool,
+    /// Text to show between given ticks
+    pub captions: Vec<Caption>,
+    /// Size, frame rate and chart ranges of the output
+    pub output: OutputConfig,
+}
+
+pub fn parking_lot_anim(config: &ParkingLotAnimConfig, pool: &Pool) -> Result<(), Box<dyn Error>> {
+    parking_lot_frames(config)?.save_gif(&config.filename, pool)
+}
+
+/// Simulate every tick of the animation
+pub fn parking_lot_frames(config: &ParkingLotAnimConfig) -> Result<Animation, Box<dyn Error>> {
+    let theme = &config.output.theme;
+    let num_hops = config.bottleneck_intersend.len();
+    if config.cross_flows.len() != num_hops {
+        return Err(format!(
+            "{} bottlenecks need as many cross flows, not {}",
+            num_hops,
+            config.cross_flows.len()
+        )
+        .into());
+    }
+
+    let mut layout = Layout::new(&Topology {
+        hops: vec![config.bufsize; num_hops],
+        ret_bufsize: 0,
+        num_flows: 1,
+        flow_sep: 0.,
+    });
+    // Make room for the cross flows above the long flow
+    layout.y_range.end = CROSS_Y + PKT_HEIGHT * 4.;
+    let mut anim = Animation::new(config.output.canvas(
+        (1600, layout.canvas_height()),
+        layout.x_range.clone(),
+        layout.y_range.clone(),
+    ));
+
+    // The long flow's acks come straight back; we don't model a congested
+    // return path here
+    let long_rtt = config.long_flow.rtt;
+    let long_sink = Rc::new(RefCell::new(config.long_flow.sink(layout.sinks[0])));
+    let long_returnpath = Rc::new(RefCell::new(Transport::new(
+        long_rtt / 2,
+        long_sink.clone(),
+    )));
+    let long_acker = Rc::new(RefCell::new(Acker {
+        rcv_coord: layout.receivers[0].0,
+        snd_coord: layout.receivers[0].1,
+        next: long_returnpath.clone(),
+    }));
+    // The rest of the rtt is split evenly between the links on the forward path
+    let long_link_delay = (long_rtt - long_rtt / 2) / (num_hops as u64 + 1);
+
+    let bottlenecks: Vec<_> = (0..num_hops)
+        .map(|i| {
+            Rc::new(RefCell::new(Bottleneck::<dyn Element>::new(
+                layout.hops[i],
+                config.bufsize,
+                vec![config.bottleneck_intersend[i]],
+                vec![],
+                true,
+            )))
+        })
+        .collect();
+
+    // The transports that carry the long flow, in order. The first goes to the
+    // first bottleneck and the last to the receiver
+    let mut long_links: Vec<Rc<RefCell<dyn Element>>> = Vec::new();
+    let mut long_transports = Vec::new();
+    for bottleneck in &bottlenecks {
+        let link = Rc::new(RefCell::new(Transport::new(
+            long_link_delay,
+            bottleneck.clone(),
+        )));
+        long_links.push(link.clone());
+        long_transports.push(link);
+    }
+    let long_departure = Rc::new(RefCell::new(Transport::new(
+        long_link_delay,
+        long_acker.clone(),
+    )));
+    long_links.push(long_departure.clone());
+
+    // (sender coord, arrival, departure, acker, returnpath, sink) for each cross
+    // flow
+    let mut cross = Vec::new();
+    for (i, flow) in config.cross_flows.iter().enumerate() {
+        let bottleneck = bottlenecks[i].borrow();
+        let sender = Coord(bottleneck.get_enqueue_coord().0 - CROSS_DX, CROSS_Y);
+        let receiver = Coord(bottleneck.get_dequeue_coord().0 + CROSS_DX, CROSS_Y);
+        let ack_offset = Coord(0., PKT_HEIGHT * 2.);
+
+        let sink = Rc::new(RefCell::new(flow.sink(sender.add(ack_offset))));
+        let returnpath = Rc::new(RefCell::new(Transport::new(flow.rtt / 2, sink.clone())));
+        let acker = Rc::new(RefCell::new(Acker {
+            rcv_coord: receiver,
+            snd_coord: receiver.add(ack_offset),
+            next: returnpath.clone(),
+        }));
+        let departure = Rc::new(RefCell::new(Transport::new(flow.rtt / 4, acker.clone())));
+        let arrival = Rc::new(RefCell::new(Transport::new(
+            flow.rtt - flow.rtt / 2 - flow.rtt / 4,
+            bottlenecks[i].clone(),
+        )));
+        cross.push((sender, arrival, departure, acker, returnpath, sink));
+    }
+
+    // A router after each bottleneck sends the long flow (address 0) on to
+    // the next hop and cross flow `i` (address `i + 1`) out to its receiver
+    let routers: Vec<_> = bottlenecks
+        .iter()
+        .enumerate()
+        .map(|(i, bottleneck)| {
+            let mut router = Router::<dyn Element>::new(bottleneck.borrow().get_dequeue_coord());
+            router.add_route(0, long_links[i + 1].clone());
+            router.add_route(i as u16 + 1, cross[i].2.clone());
+            Rc::new(RefCell::new(router))
+        })
+        .collect();
+    // Bottlenecks pick their next element by address, and every address
+    // leads to the router
+    for (bottleneck, router) in bottlenecks.iter().zip(&routers) {
+        let router: Rc<RefCell<dyn Element>> = router.clone();
+        bottleneck.borrow_mut().set_next(vec![router; num_hops + 1]);
+    }
+
+    let long_template = Packet::new(
+        DATA_PKT_WIDTH,
+        layout.senders[0],
+        0,
+        0,
+        Style::from(config.long_flow.color).filled(),
+    );
+    let mut sources =
+        vec![config
+            .long_flow
+            .source::<dyn Element>(long_template, long_transports[0].clone(), 0)];
+    for (i, (flow, (sender, arrival, ..))) in config.cross_flows.iter().zip(&cross).enumerate() {
+        let template = Packet::new(
+            DATA_PKT_WIDTH,
+            *sender,
+            i as u16 + 1,
+            i as u16 + 1,
+            Style::from(flow.color).filled(),
+        );
+        sources.push(flow.source(template, arrival.clone(), i as u64 + 1));
+    }
+
+    let mut diagram = Diagram::new();
+    diagram.node(NodeKind::Host, layout.host_coord(0));
+    diagram.node(NodeKind::Receiver, layout.receiver_coord(0));
+    diagram.link(&*long_transports[0].borrow(), layout.senders[0]);
+    for (i, bottleneck) in bottlenecks.iter().enumerate() {
+        let from = bottleneck.borrow().get_dequeue_coord();
+        diagram.node(NodeKind::Router, from);
+        match long_transports.get(i + 1) {
+            Some(link) => diagram.link(&*link.borrow(), from),
+            None => diagram.link(&*long_departure.borrow(), from),
+        }
+    }
+    diagram.link(&*long_returnpath.borrow(), layout.receivers[0].1);
+    for (i, (sender, arrival, departure, acker, returnpath, _)) in cross.iter().enumerate() {
+        diagram.node(NodeKind::Host, sender.add(Coord(0., PKT_HEIGHT)));
+        diagram.node(
+            NodeKind::Receiver,
+            acker.borrow().rcv_coord.add(Coord(0., PKT_HEIGHT)),
+        );
+        diagram.link(&*arrival.borrow(), *sender);
+        diagram.link(
+            &*departure.borrow(),
+            bottlenecks[i].borrow().get_dequeue_coord(),
+        );
+        diagram.link(&*returnpath.borrow(), acker.borrow().snd_coord);
+    }
+
+    let mut annotations = Annotations::new();
+    let mut legend = vec![(
+        String::from("Long flow"),
+        Style::from(config.long_flow.color).filled(),
+    )];
+    legend.extend(config.cross_flows.iter().enumerate().map(|(i, f)| {
+        (
+            format!("Cross traffic {}", i + 1),
+            Style::from(f.color).filled(),
+        )
+    }));
+    annotations.legend(layout.legend_coord(), legend);
+    if config.show_clock {
+        annotations.clock(layout.clock_coord());
+    }
+    annotations.captions(
+        Coord(0., layout.y_range.start + 1.),
+        config.captions.clone(),
+    );
+
+    for tick in 0..config.num_ticks {
+        let mut frame = diagram.draw(theme);
+
+        // Produce packets
+        for source in &mut sources {
+            source.tick();
+        }
+
+        // Long flow
+        for ((link, bottleneck), router) in long_transports.iter().zip(&bottlenecks).zip(&routers) {
+            let mut link = link.borrow_mut();
+            link.tick();
+            frame.extend(link.draw(theme));
+
+            let mut bottleneck = bottleneck.borrow_mut();
+            bottleneck.tick();
+            frame.extend(bottleneck.draw(theme));
+
+            let mut router = router.borrow_mut();
+            router.tick();
+            frame.extend(router.draw(theme));
+        }
+
+        {
+            let mut long_departure = long_departure.borrow_mut();
+            long_departure.tick();
+            frame.extend(long_departure.draw(theme));
+        }
+
+        {
+            let mut long_acker = long_acker.borrow_mut();
+            long_acker.tick();
+            frame.extend(long_acker.draw(theme));
+        }
+
+        {
+            let mut long_returnpath = long_returnpath.borrow_mut();
+            long_returnpath.tick();
+            frame.extend(long_returnpath.draw(theme));
+        }
+
+        {
+            let mut long_sink = long_sink.borrow_mut();
+            long_sink.tick();
+            frame.extend(long_sink.draw(theme));
+        }
+
+        // Cross flows
+        for (_, arrival, departure, acker, returnpath, sink) in &cross {
+            let mut arrival = arrival.borrow_mut();
+            arrival.tick();
+            frame.extend(arrival.draw(theme));
+
+            let mut departure = departure.borrow_mut();
+            departure.tick();
+            frame.extend(departure.draw(theme));
+
+            let mut acker = acker.borrow_mut();
+            acker.tick();
+            frame.extend(acker.draw(theme));
+
+            let mut returnpath = returnpath.borrow_mut();
+            returnpath.tick();
+            frame.extend(returnpath.draw(theme));
+
+            let mut sink = sink.borrow_mut();
+            sink.tick();
+            frame.extend(sink.draw(theme));
+        }
+
+        frame.extend(annotations.draw(tick, theme));
+
+        anim.frames.push(frame);
+    }
+
+    if check::is_enabled() {
+        let mut in_flight = long_returnpath.borrow().get_pkts();
+        for link in &long_links {
+            in_flight.extend(link.borrow().get_pkts());
+        }
+        for bottleneck in &bottlenecks {
+            in_flight.extend(bottleneck.borrow().get_pkts());
+        }
+        for (_, arrival, departure, _, returnpath, _) in &cross {
+            in_flight.extend(arrival.borrow().get_pkts());
+            in_flight.extend(departure.borrow().get_pkts());
+            in_flight.extend(returnpath.borrow().get_pkts());
+        }
+        check::finish(in_flight)?;
+    }
+    Ok(config.output.finish(anim))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_missing_cross_flows() {
+        let config = ParkingLotAnimConfig {
+            bufsize: 8,
+            bottleneck_intersend: vec![10, 10],
+            cross_flows: vec![FlowConfig::default()],
+            num_ticks: 10,
+            ..Default::default()
+        };
+        let err = parking_lot_frames(&config).err().unwrap();
+        assert!(err.to_string().contains("2 bottlenecks"));
+    }
+}
@@ -1,13 +1,20 @@
 //! Animation of the entire path
+use crate::annotation::{Annotations, Caption};
 use crate::base::{Coord, Element, Packet, DATA_PKT_WIDTH, PKT_HEIGHT};
 use crate::bottleneck::Bottleneck;
+use crate::check;
+use crate::layout::{Layout, Topology};
+use crate::node::{Diagram, NodeKind};
+use crate::output::{Animation, OutputConfig};
+use crate::pool::Pool;
+use crate::scene::{Color, Style};
 use crate::simple_elems::{Acker, Sink};
 use crate::transport::Transport;
-use plotters::prelude::*;
 use std::cell::RefCell;
 use std::error::Error;
 use std::rc::Rc;
 
+#[derive(Clone, Default)]
 pub struct PathAnimConfig {
     /// Where to output the .gif file
     pub filename: String,
@@ -21,17 +28,37 @@ pub struct PathAnimConfig {
     pub num_extra_packets: u64,
     /// Number of ticks to animate
     pub num_ticks: u64,
+    /// Should we draw the current tick?
+    pub show_clock: bool,
+    /// Text to show between given ticks
+    pub captions: Vec<Caption>,
+    /// Size, frame rate and chart ranges of the output
+    pub output: OutputConfig,
 }
 
-pub fn path_anim(config: &PathAnimConfig) -> Result<(), Box<dyn Error>> {
-    let root = BitMapBackend::gif(&config.filename, (1600, 100), 16)?.into_drawing_area();
+/// Size of the animation in pixels
+pub const CANVAS_SIZE: (u32, u32) = (1600, 100);
+
+pub fn path_anim(config: &PathAnimConfig, pool: &Pool) -> Result<(), Box<dyn Error>> {
+    path_frames(config)?.save_gif(&config.filename, pool)
+}
+
+/// Simulate every tick of the animation
+pub fn path_frames(config: &PathAnimConfig) -> Result<Animation, Box<dyn Error>> {
+    let theme = &config.output.theme;
+    let layout = Layout::new(&Topology {
+        hops: vec![config.bufsize],
+        ret_bufsize: config.bufsize,
+        num_flows: 1,
+        flow_sep: 0.,
+    });
 
     let sink = Rc::new(RefCell::new(Sink {
-        coord: Coord(-9., -PKT_HEIGHT * 2.),
+        coord: layout.sinks[0],
     }));
     let returnpath2 = Rc::new(RefCell::new(Transport::new(16, sink.clone())));
     let ret_bottleneck = Rc::new(RefCell::new(Bottleneck::new(
-        Coord(DATA_PKT_WIDTH * config.bufsize as f64, -PKT_HEIGHT * 2.),
+        layout.ret_hop,
         config.bufsize,
         vec![config.bottleneck_intersend],
         vec![returnpath2.clone()],
@@ -39,13 +66,13 @@ pub fn path_anim(config: &PathAnimConfig) -> Result<(), Box<dyn Error>> {
     )));
     let returnpath1 = Rc::new(RefCell::new(Transport::new(16, ret_bottleneck.clone())));
     let acker = Rc::new(RefCell::new(Acker {
-        rcv_coord: Coord(9., 0.),
-        snd_coord: Coord(9., -PKT_HEIGHT * 2.),
+        rcv_coord: layout.receivers[0].0,
+        snd_coord: layout.receivers[0].1,
         next: returnpath1.clone(),
     }));
     let departure = Rc::new(RefCell::new(Transport::new(16, acker.clone())));
     let bottleneck = Rc::new(RefCell::new(Bottleneck::new(
-        Coord(0., 0.),
+        layout.hops[0],
         config.bufsize,
         vec![config.bottleneck_intersend],
         vec![departure.clone()],
@@ -53,67 +80,111 @@ pub fn path_anim(config: &PathAnimConfig) -> Result<(), Box<dyn Error>> {
     )));
     let arrival = Rc::new(RefCell::new(Transport::new(32, bottleneck.clone())));
 
+    let mut diagram = Diagram::new();
+    diagram.node(NodeKind::Host, layout.host_coord(0));
+    diagram.node(NodeKind::Router, bottleneck.borrow().get_dequeue_coord());
+    diagram.node(
+        NodeKind::Router,
+        ret_bottleneck.borrow().get_dequeue_coord(),
+    );
+    diagram.node(NodeKind::Receiver, layout.receiver_coord(0));
+    diagram.link(&*arrival.borrow(), layout.senders[0]);
+    diagram.link(
+        &*departure.borrow(),
+        bottleneck.borrow().get_dequeue_coord(),
+    );
+    diagram.link(&*returnpath1.borrow(), layout.receivers[0].1);
+    diagram.link(
+        &*returnpath2.borrow(),
+        ret_bottleneck.borrow().get_dequeue_coord(),
+    );
+
+    let mut annotations = Annotations::new();
+    let label_offset = Coord(
+        DATA_PKT_WIDTH * (config.bufsize as f64 / 2. + 1.),
+        PKT_HEIGHT * 1.1,
+    );
+    annotations.label(
+        "Bottleneck",
+        bottleneck.borrow().get_enqueue_coord(),
+        label_offset,
+    );
+    annotations.label(
+        "Receiver",
+        acker.borrow().get_enqueue_coord(),
+        Coord(0., PKT_HEIGHT * 1.1),
+    );
+    if config.show_clock {
+        annotations.clock(layout.clock_coord());
+    }
+    annotations.captions(layout.caption_coord(), config.captions.clone());
+
     let mut num_packets = 0;
+    let mut anim = Animation::new(config.output.canvas(
+        CANVAS_SIZE,
+        layout.x_range.clone(),
+        layout.y_range.clone(),
+    ));
     for tick in 0..config.num_ticks {
-        root.fill(&WHITE)?;
-        let chart = ChartBuilder::on(&root).build_cartesian_2d(-10.0..10.0, -5.0..5.0)?;
+        let mut frame = diagram.draw(theme);
 
         let mut arrival = arrival.borrow_mut();
 
         if tick % config.sender_intersend == 0
             || (tick % (config.sender_intersend / 2) == 0 && num_packets < config.num_extra_packets)
         {
-            arrival.enqueue(&Packet {
-                size: DATA_PKT_WIDTH,
-                coord: Coord(-10., 0.),
-                addr: 0,
-                style: ShapeStyle::from(RED).filled(),
-            });
+            let mut pkt = Packet::new(
+                DATA_PKT_WIDTH,
+                layout.senders[0],
+                0,
+                0,
+                Style::from(Color::RED).filled(),
+            );
+            check::created(&mut pkt);
+            arrival.enqueue(&pkt);
             num_packets += 1;
         }
 
         arrival.tick();
-        for e in arrival.draw() {
-            chart.plotting_area().draw(&e)?;
-        }
+        frame.extend(arrival.draw(theme));
 
         let mut bottleneck = bottleneck.borrow_mut();
         bottleneck.tick();
-        for e in bottleneck.draw() {
-            chart.plotting_area().draw(&e)?;
-        }
+        frame.extend(bottleneck.draw(theme));
 
         let mut departure = departure.borrow_mut();
         departure.tick();
-        for e in departure.draw() {
-            chart.plotting_area().draw(&e)?;
-        }
+        frame.extend(departure.draw(theme));
 
         let mut acker = acker.borrow_mut();
         acker.tick();
-        for e in acker.draw() {
-            chart.plotting_area().draw(&e)?;
-        }
+        frame.extend(acker.draw(theme));
 
         let mut returnpath1 = returnpath1.borrow_mut();
         returnpath1.tick();
-        for e in returnpath1.draw() {
-            chart.plotting_area().draw(&e)?;
-        }
+        frame.extend(returnpath1.draw(theme));
 
         let mut ret_bottleneck = ret_bottleneck.borrow_mut();
         ret_bottleneck.tick();
-        for e in ret_bottleneck.draw() {
-            chart.plotting_area().draw(&e)?;
-        }
+        frame.extend(ret_bottleneck.draw(theme));
 
         let mut returnpath2 = returnpath2.borrow_mut();
         returnpath2.tick();
-        for e in returnpath2.draw() {
-            chart.plotting_area().draw(&e)?;
-        }
+        frame.extend(returnpath2.draw(theme));
+
+        frame.extend(annotations.draw(tick, theme));
+
+        anim.frames.push(frame);
+    }
 
-        root.present()?;
+    if check::is_enabled() {
+        let mut in_flight = arrival.borrow().get_pkts();
+        in_flight.extend(bottleneck.borrow().get_pkts());
+        in_flight.extend(departure.borrow().get_pkts());
+        in_flight.extend(returnpath1.borrow().get_pkts());
+        in_flight.extend(ret_bottleneck.borrow().get_pkts());
+        in_flight.extend(returnpath2.borrow().get_pkts());
+        check::finish(in_flight)?;
     }
-    Ok(())
+    Ok(config.output.finish(anim))
 }
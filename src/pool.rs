@@ -0,0 +1,237 @@
+//! A fixed set of threads shared by every animation. Jobs (usually simulating
+//! an animation) and the frames they rasterize run on the same threads, so the
+//! number of threads and of animations held in memory is bounded by the number
+//! of cores, however many animations we render
+use std::any::Any;
+use std::collections::{BTreeMap, VecDeque};
+use std::error::Error;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+/// Renders one or more outputs, using the pool for the heavy lifting
+pub type Job = Box<dyn FnOnce(&Pool) -> Result<(), Box<dyn Error>> + Send>;
+
+/// A small piece of a job, e.g. rasterizing a frame
+type Task = Box<dyn FnOnce() + Send>;
+
+enum Work {
+    Task(Task),
+    Job(Job),
+}
+
+#[derive(Default)]
+struct Queue {
+    /// Tasks go before jobs, so jobs that started finish before new ones
+    /// start
+    tasks: VecDeque<Task>,
+    jobs: VecDeque<Job>,
+    /// Number of jobs started but not finished. They may queue more tasks
+    num_running: usize,
+}
+
+pub struct Pool {
+    num_threads: usize,
+    queue: Mutex<Queue>,
+    /// Signalled when something is queued or a job finishes
+    changed: Condvar,
+}
+
+impl Pool {
+    /// A pool with a thread per core
+    pub fn new() -> Self {
+        Self::with_threads(thread::available_parallelism().map_or(1, |n| n.get()))
+    }
+
+    pub fn with_threads(num_threads: usize) -> Self {
+        Self {
+            num_threads: usize::max(1, num_threads),
+            queue: Mutex::new(Queue::default()),
+            changed: Condvar::new(),
+        }
+    }
+
+    /// Run every job and report every one that failed or panicked
+    pub fn run(&self, jobs: Vec<Job>) -> Result<(), Box<dyn Error>> {
+        self.queue.lock().unwrap().jobs.extend(jobs);
+        let errors = Mutex::new(Vec::new());
+        thread::scope(|s| {
+            for _ in 0..self.num_threads {
+                s.spawn(|| self.work(&errors));
+            }
+        });
+        let errors = errors.into_inner().unwrap();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n").into())
+        }
+    }
+
+    /// Take tasks and jobs until there is nothing left to do
+    fn work(&self, errors: &Mutex<Vec<String>>) {
+        while let Some(work) = self.next() {
+            let job = match work {
+                Work::Task(task) => {
+                    task();
+                    continue;
+                }
+                Work::Job(job) => job,
+            };
+            let res = match panic::catch_unwind(AssertUnwindSafe(|| job(self))) {
+                Ok(res) => res.map_err(|e| e.to_string()),
+                Err(payload) => Err(panic_message(payload)),
+            };
+            if let Err(e) = res {
+                errors.lock().unwrap().push(e);
+            }
+            self.queue.lock().unwrap().num_running -= 1;
+            self.changed.notify_all();
+        }
+    }
+
+    /// Wait for a task or a job. `None` once every job finished
+    fn next(&self) -> Option<Work> {
+        let mut queue = self.queue.lock().unwrap();
+        loop {
+            if let Some(task) = queue.tasks.pop_front() {
+                return Some(Work::Task(task));
+            }
+            if let Some(job) = queue.jobs.pop_front() {
+                queue.num_running += 1;
+                return Some(Work::Job(job));
+            }
+            if queue.num_running == 0 {
+                return None;
+            }
+            queue = self.changed.wait(queue).unwrap();
+        }
+    }
+
+    /// Run a queued task, if there is one, on this thread
+    fn help(&self) -> bool {
+        let task = self.queue.lock().unwrap().tasks.pop_front();
+        match task {
+            Some(task) => {
+                task();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Compute `f(i)` for every `i` in `0..n` on the pool and hand the
+    /// results to `consume` in order of `i`. Only a few results are computed
+    /// ahead of `consume`, which bounds how many we hold. The caller works on
+    /// queued tasks while it waits, so jobs can call this
+    pub fn ordered<R, F, C>(&self, n: usize, f: F, mut consume: C) -> Result<(), Box<dyn Error>>
+    where
+        R: Send + 'static,
+        F: Fn(usize) -> R + Send + Sync + 'static,
+        C: FnMut(R) -> Result<(), Box<dyn Error>>,
+    {
+        let window = self.num_threads * 4;
+        let f = Arc::new(f);
+        let (tx, rx) = mpsc::channel();
+        let mut done = BTreeMap::new();
+        let mut num_queued = 0;
+        for i in 0..n {
+            while num_queued < usize::min(n, i + window) {
+                let (f, tx, j) = (f.clone(), tx.clone(), num_queued);
+                self.queue
+                    .lock()
+                    .unwrap()
+                    .tasks
+                    .push_back(Box::new(move || {
+                        let res = panic::catch_unwind(AssertUnwindSafe(|| f(j)));
+                        // The receiver is gone if an earlier result failed
+                        let _ = tx.send((j, res.map_err(panic_message)));
+                    }));
+                self.changed.notify_one();
+                num_queued += 1;
+            }
+            while !done.contains_key(&i) {
+                match rx.try_recv() {
+                    Ok((j, res)) => {
+                        done.insert(j, res);
+                    }
+                    Err(_) if self.help() => {}
+                    // Whoever took our tasks will send their results
+                    Err(_) => {
+                        let (j, res) = rx.recv()?;
+                        done.insert(j, res);
+                    }
+                }
+            }
+            consume(done.remove(&i).unwrap()?)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for Pool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What a thread panicked with, if it was a message
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(msg) => *msg,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(msg) => msg.to_string(),
+            Err(_) => String::from("panicked"),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ordered_keeps_order() {
+        let pool = Pool::with_threads(3);
+        let jobs: Vec<Job> = (0..4)
+            .map(|k| -> Job {
+                Box::new(move |pool| {
+                    let mut res = Vec::new();
+                    pool.ordered(
+                        50,
+                        move |i| i * k,
+                        |r| {
+                            res.push(r);
+                            Ok(())
+                        },
+                    )?;
+                    assert_eq!(res, (0..50).map(|i| i * k).collect::<Vec<_>>());
+                    Ok(())
+                })
+            })
+            .collect();
+        pool.run(jobs).unwrap();
+    }
+
+    #[test]
+    fn reports_panics() {
+        let pool = Pool::with_threads(2);
+        let jobs: Vec<Job> = vec![
+            Box::new(|_| Ok(())),
+            Box::new(|_| panic!("job failed")),
+            Box::new(|pool| {
+                pool.ordered(
+                    10,
+                    |i| {
+                        assert_ne!(i, 7, "task failed");
+                    },
+                    |_| Ok(()),
+                )
+            }),
+        ];
+        let err = pool.run(jobs).unwrap_err().to_string();
+        assert!(err.contains("job failed"));
+        assert!(err.contains("task failed"));
+    }
+}
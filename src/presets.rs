@@ -0,0 +1,526 @@
+//! Configurations of the animations we publish, shared by the binary and the
+//! golden-frame tests
+use crate::ack_path::AckChannel;
+use crate::annotation::Caption;
+use crate::base::DATA_PKT_WIDTH;
+use crate::bottleneck::{CapacitySchedule, Rate};
+use crate::dumbbell_anim::{DumbbellAnimConfig, FlowConfig};
+use crate::ecn_anim::{EcnAnimConfig, EcnFlowConfig};
+use crate::elem_anim::ElemAnimConfig;
+use crate::fair_anim::FairAnimConfig;
+use crate::parking_lot_anim::ParkingLotAnimConfig;
+use crate::path_anim::PathAnimConfig;
+use crate::scene::Color;
+use crate::sender::EcnResponse;
+use crate::sweep::SweepConfig;
+use crate::traffic::{Pattern, Period};
+use crate::two_bottlenecks_anim::TwoBottlenecksAnimConfig;
+use crate::wifi::Mac;
+use crate::wifi_anim::WifiAnimConfig;
+
+pub fn two_bottlenecks() -> Vec<TwoBottlenecksAnimConfig> {
+    vec![
+        TwoBottlenecksAnimConfig {
+            filename: String::from("two-bottlenecks-copa.gif"),
+            bufsize1: 8,
+            bottleneck1_intersend: vec![73, 1, 1, 1, 1, 1, 1, 1],
+            bufsize2: 8,
+            bottleneck2_intersend: vec![10],
+            sender_intersend: 15,
+            num_extra_packets: 0,
+            num_ticks: 640,
+            draw_buffer: (true, true),
+            ..Default::default()
+        },
+        // The same bunching as above, but from acks aggregated on the return
+        // path rather than a bursty return link
+        TwoBottlenecksAnimConfig {
+            filename: String::from("two-bottlenecks-copa-ack-aggregation.gif"),
+            bufsize1: 8,
+            bottleneck1_intersend: vec![10],
+            bufsize2: 8,
+            bottleneck2_intersend: vec![10],
+            sender_intersend: 15,
+            num_extra_packets: 0,
+            num_ticks: 640,
+            draw_buffer: (true, true),
+            ack_channel: AckChannel::Aggregate { interval: 80 },
+            ..Default::default()
+        },
+        TwoBottlenecksAnimConfig {
+            filename: String::from("two-bottlenecks-ideal.gif"),
+            bufsize1: 8,
+            bottleneck1_intersend: vec![10],
+            bufsize2: 4,
+            bottleneck2_intersend: vec![10],
+            sender_intersend: 10,
+            num_extra_packets: 7,
+            num_ticks: 640,
+            draw_buffer: (true, false),
+            ..Default::default()
+        },
+        TwoBottlenecksAnimConfig {
+            filename: String::from("two-bottlenecks-adversary.gif"),
+            bufsize1: 8,
+            bottleneck1_intersend: vec![10],
+            bufsize2: 4,
+            //bottleneck2_intersend: vec![5, 0, 0, 20, 10, 0, 30, 15],
+            bottleneck2_intersend: vec![0, 0, 30],
+            sender_intersend: 10,
+            num_extra_packets: 7,
+            num_ticks: 640,
+            draw_buffer: (true, false),
+            ..Default::default()
+        },
+    ]
+}
+
+pub fn elem() -> Vec<ElemAnimConfig> {
+    vec![
+        ElemAnimConfig {
+            filename: String::from("elem-ideal.gif"),
+            bufsize: 8,
+            bottleneck_intersend: vec![10],
+            sender_intersend: vec![10],
+            num_ticks: 640,
+            ..Default::default()
+        },
+        ElemAnimConfig {
+            filename: String::from("elem-agg.gif"),
+            bufsize: 8,
+            bottleneck_intersend: vec![70, 1, 1, 1, 1, 1, 1],
+            sender_intersend: vec![11],
+            num_ticks: 640,
+            ..Default::default()
+        },
+        ElemAnimConfig {
+            filename: String::from("elem-random.gif"),
+            bufsize: 10,
+            //bottleneck_intersend: vec![10, 5, 2, 15, 20, 2, 12, 12, 12, 5],
+            bottleneck_intersend: vec![5, 1, 1, 1, 1, 12, 20, 1, 10, 6, 6],
+            sender_intersend: vec![6],
+            num_ticks: 640,
+            ..Default::default()
+        },
+        ElemAnimConfig {
+            filename: String::from("elem-tbf.gif"),
+            bufsize: 10,
+            //bottleneck_intersend: vec![10, 5, 2, 15, 20, 2, 12, 12, 12, 5],
+            bottleneck_intersend: vec![1, 1, 1, 10, 10, 10, 10, 10, 10],
+            sender_intersend: vec![1, 1, 1, 1, 1, 1, 1, 1, 90],
+            num_ticks: 640,
+            ..Default::default()
+        },
+    ]
+}
+
+pub fn fair() -> Vec<FairAnimConfig> {
+    vec![
+        FairAnimConfig {
+            filename: String::from("fair.gif"),
+            bufsize: 8,
+            bottleneck_intersend: 5,
+            sender_intersend: (10, 10),
+            extra_packets: 8,
+            num_ticks: 640,
+            ..Default::default()
+        },
+        FairAnimConfig {
+            filename: String::from("unfair.gif"),
+            bufsize: 8,
+            bottleneck_intersend: 10,
+            sender_intersend: (11, 110),
+            extra_packets: 8,
+            num_ticks: 640,
+            captions: vec![Caption {
+                text: String::from("Flow B rarely gets a packet through"),
+                start: 320,
+                end: 640,
+            }],
+            ..Default::default()
+        },
+    ]
+}
+
+pub fn path() -> Vec<PathAnimConfig> {
+    vec![
+        PathAnimConfig {
+            filename: String::from("ideal-slow.gif"),
+            bufsize: 8,
+            bottleneck_intersend: 10,
+            sender_intersend: 20,
+            num_extra_packets: 0,
+            num_ticks: 640,
+            ..Default::default()
+        },
+        PathAnimConfig {
+            filename: String::from("ideal-correct.gif"),
+            bufsize: 8,
+            bottleneck_intersend: 10,
+            sender_intersend: 10,
+            num_extra_packets: 4,
+            num_ticks: 640,
+            ..Default::default()
+        },
+        PathAnimConfig {
+            filename: String::from("ideal-fast.gif"),
+            bufsize: 8,
+            bottleneck_intersend: 10,
+            sender_intersend: 5,
+            num_extra_packets: 0,
+            num_ticks: 640,
+            ..Default::default()
+        },
+    ]
+}
+
+pub fn dumbbell() -> Vec<DumbbellAnimConfig> {
+    let cbr = Pattern::Cbr { intersend: 10 };
+    vec![
+        DumbbellAnimConfig {
+            filename: String::from("dumbbell.gif"),
+            bufsize: 8,
+            bottleneck_intersend: 5,
+            capacity: None,
+            flows: vec![
+                FlowConfig {
+                    pattern: Pattern::Cbr { intersend: 15 },
+                    rtt: 60,
+                    color: Color::RED,
+                    ..Default::default()
+                },
+                FlowConfig {
+                    pattern: Pattern::Cbr { intersend: 15 },
+                    rtt: 80,
+                    color: Color::GREEN,
+                    start: 3,
+                    ..Default::default()
+                },
+                FlowConfig {
+                    pattern: Pattern::Cbr { intersend: 15 },
+                    rtt: 120,
+                    color: Color::BLUE,
+                    start: 6,
+                    ..Default::default()
+                },
+            ],
+            num_ticks: 640,
+            show_clock: true,
+            ..Default::default()
+        },
+        // A short flow finishes while a long flow runs, then a third flow joins
+        DumbbellAnimConfig {
+            filename: String::from("dumbbell-join.gif"),
+            bufsize: 10,
+            bottleneck_intersend: 8,
+            capacity: None,
+            flows: vec![
+                FlowConfig {
+                    pattern: cbr.clone(),
+                    rtt: 80,
+                    color: Color::RED,
+                    ..Default::default()
+                },
+                FlowConfig {
+                    pattern: cbr.clone(),
+                    rtt: 80,
+                    color: Color::BLUE,
+                    start: 100,
+                    size: Some(10),
+                    ..Default::default()
+                },
+                FlowConfig {
+                    pattern: cbr.clone(),
+                    rtt: 80,
+                    color: Color::GREEN,
+                    start: 350,
+                    ..Default::default()
+                },
+            ],
+            num_ticks: 640,
+            show_clock: true,
+            captions: vec![
+                Caption {
+                    text: String::from("A short flow starts"),
+                    start: 100,
+                    end: 200,
+                },
+                Caption {
+                    text: String::from("A new flow joins the busy link"),
+                    start: 350,
+                    end: 450,
+                },
+            ],
+            ..Default::default()
+        },
+        // The link rate halves, then goes out for a while
+        DumbbellAnimConfig {
+            filename: String::from("dumbbell-capacity.gif"),
+            bufsize: 10,
+            bottleneck_intersend: 5,
+            capacity: Some(CapacitySchedule {
+                rate: Rate::Step {
+                    at: 200,
+                    before: DATA_PKT_WIDTH / 5.,
+                    after: DATA_PKT_WIDTH / 10.,
+                },
+                outages: vec![(400, 460)],
+            }),
+            flows: vec![
+                FlowConfig {
+                    pattern: cbr.clone(),
+                    rtt: 60,
+                    color: Color::RED,
+                    ..Default::default()
+                },
+                FlowConfig {
+                    pattern: cbr,
+                    rtt: 60,
+                    color: Color::BLUE,
+                    start: 5,
+                    ..Default::default()
+                },
+            ],
+            num_ticks: 640,
+            show_clock: true,
+            captions: vec![
+                Caption {
+                    text: String::from("Link rate halves"),
+                    start: 200,
+                    end: 300,
+                },
+                Caption {
+                    text: String::from("Link outage"),
+                    start: 400,
+                    end: 460,
+                },
+            ],
+            ..Default::default()
+        },
+    ]
+}
+
+pub fn parking_lot() -> Vec<ParkingLotAnimConfig> {
+    vec![ParkingLotAnimConfig {
+        filename: String::from("parking-lot.gif"),
+        bufsize: 8,
+        bottleneck_intersend: vec![10, 10, 10],
+        long_flow: FlowConfig {
+            pattern: Pattern::Cbr { intersend: 20 },
+            rtt: 120,
+            color: Color::RED,
+            ..Default::default()
+        },
+        cross_flows: vec![
+            FlowConfig {
+                pattern: Pattern::Poisson { rate: 0.05 },
+                rtt: 40,
+                color: Color::GREEN,
+                ..Default::default()
+            },
+            FlowConfig {
+                pattern: Pattern::OnOff {
+                    on: Period::Exponential { mean: 100. },
+                    off: Period::Exponential { mean: 100. },
+                    intersend: 10,
+                },
+                rtt: 40,
+                color: Color::BLUE,
+                ..Default::default()
+            },
+            FlowConfig {
+                pattern: Pattern::OnOff {
+                    on: Period::Pareto {
+                        scale: 40.,
+                        shape: 1.5,
+                    },
+                    off: Period::Pareto {
+                        scale: 40.,
+                        shape: 1.5,
+                    },
+                    intersend: 10,
+                },
+                rtt: 40,
+                color: Color::MAGENTA,
+                ..Default::default()
+            },
+        ],
+        num_ticks: 640,
+        show_clock: true,
+        ..Default::default()
+    }]
+}
+
+pub fn ecn() -> Vec<EcnAnimConfig> {
+    vec![
+        EcnAnimConfig {
+            filename: String::from("ecn.gif"),
+            bufsize: 16,
+            ecn_threshold: Some(4),
+            bottleneck_intersend: 8,
+            flows: vec![
+                EcnFlowConfig {
+                    response: EcnResponse::Classic,
+                    init_cwnd: 8.,
+                    pacing_gain: None,
+                    rtt: 20,
+                    rtt_schedule: vec![],
+                    detour: None,
+                    color: Color::RED,
+                },
+                EcnFlowConfig {
+                    response: EcnResponse::Dctcp { gain: 1. / 16. },
+                    init_cwnd: 8.,
+                    pacing_gain: None,
+                    rtt: 20,
+                    rtt_schedule: vec![],
+                    detour: None,
+                    color: Color::BLUE,
+                },
+            ],
+            num_ticks: 960,
+            show_clock: true,
+            ..Default::default()
+        },
+        // Same window, but one flow sends it in bursts and the other spreads it
+        // over the RTT
+        EcnAnimConfig {
+            filename: String::from("pacing.gif"),
+            bufsize: 16,
+            ecn_threshold: Some(8),
+            bottleneck_intersend: 8,
+            flows: vec![
+                EcnFlowConfig {
+                    response: EcnResponse::Dctcp { gain: 1. / 16. },
+                    init_cwnd: 12.,
+                    pacing_gain: None,
+                    rtt: 20,
+                    rtt_schedule: vec![],
+                    detour: None,
+                    color: Color::RED,
+                },
+                EcnFlowConfig {
+                    response: EcnResponse::Dctcp { gain: 1. / 16. },
+                    init_cwnd: 12.,
+                    pacing_gain: Some(1.),
+                    rtt: 20,
+                    rtt_schedule: vec![],
+                    detour: None,
+                    color: Color::BLUE,
+                },
+            ],
+            num_ticks: 640,
+            show_clock: true,
+            ..Default::default()
+        },
+        // One flow's RTT steps up, the other's path flaps to a longer route and
+        // back
+        EcnAnimConfig {
+            filename: String::from("rtt-change.gif"),
+            bufsize: 16,
+            ecn_threshold: Some(8),
+            bottleneck_intersend: 8,
+            flows: vec![
+                EcnFlowConfig {
+                    response: EcnResponse::Dctcp { gain: 1. / 16. },
+                    init_cwnd: 8.,
+                    pacing_gain: Some(1.),
+                    rtt: 20,
+                    rtt_schedule: vec![(300, 40)],
+                    detour: None,
+                    color: Color::RED,
+                },
+                EcnFlowConfig {
+                    response: EcnResponse::Dctcp { gain: 1. / 16. },
+                    init_cwnd: 8.,
+                    pacing_gain: Some(1.),
+                    rtt: 20,
+                    rtt_schedule: vec![],
+                    detour: Some((500, 700)),
+                    color: Color::BLUE,
+                },
+            ],
+            num_ticks: 900,
+            show_clock: true,
+            captions: vec![
+                Caption {
+                    text: String::from("Red flow's RTT doubles"),
+                    start: 300,
+                    end: 420,
+                },
+                Caption {
+                    text: String::from("Blue flow is rerouted"),
+                    start: 500,
+                    end: 620,
+                },
+                Caption {
+                    text: String::from("Blue flow's route is restored"),
+                    start: 700,
+                    end: 820,
+                },
+            ],
+            ..Default::default()
+        },
+    ]
+}
+
+pub fn wifi() -> Vec<WifiAnimConfig> {
+    let station = |pattern, color| FlowConfig {
+        pattern,
+        rtt: 60,
+        color,
+        ..Default::default()
+    };
+    vec![WifiAnimConfig {
+        filename: String::from("wifi.gif"),
+        bufsize: 12,
+        mac: Mac {
+            max_batch: 6,
+            overhead: 10,
+            per_pkt: 2,
+            max_backoff: 16,
+        },
+        flows: vec![
+            station(Pattern::Cbr { intersend: 15 }, Color::RED),
+            station(Pattern::Poisson { rate: 1. / 15. }, Color::GREEN),
+            FlowConfig {
+                start: 7,
+                ..station(Pattern::Cbr { intersend: 15 }, Color::BLUE)
+            },
+        ],
+        num_ticks: 640,
+        show_clock: true,
+        ..Default::default()
+    }]
+}
+
+/// How a DCTCP flow's queue behaves as its RTT grows. Sweeps
+/// `DctcpScenario::rtt`
+pub fn rtt_sweep() -> SweepConfig {
+    SweepConfig {
+        filename: String::from("dctcp-rtt-sweep.gif"),
+        param: String::from("RTT"),
+        range: 10.0..80.0,
+        num_frames: 240,
+        x_desc: String::from("Time (ticks)"),
+        y_desc: String::from("Queue (packets)"),
+        labels: true,
+        ..Default::default()
+    }
+}
+
+/// How a DCTCP flow's queue behaves as its buffer shrinks below the marking
+/// threshold. Sweeps `DctcpScenario::bufsize`, with an RTT short enough that
+/// the queue stands near the threshold
+pub fn bufsize_sweep() -> SweepConfig {
+    SweepConfig {
+        filename: String::from("dctcp-bufsize-sweep.gif"),
+        param: String::from("Buffer size"),
+        range: 2.0..32.0,
+        num_frames: 240,
+        x_desc: String::from("Time (ticks)"),
+        y_desc: String::from("Queue (packets)"),
+        labels: true,
+        ..Default::default()
+    }
+}
@@ -0,0 +1,263 @@
+//! Forwards packets to the next element by looking up their address in a
+//! routing table
+use crate::base::{Coord, Element, Packet, DATA_PKT_WIDTH};
+use crate::check;
+use crate::scene::Shape;
+use crate::simple_elems::Sink;
+use crate::theme::Theme;
+use crate::transport::Transport;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+
+/// Forwards packets instantly. Unlike `Bottleneck`, it does not queue them
+pub struct Router<N: Element + ?Sized> {
+    /// Where packets enter and leave the router
+    coord: Coord,
+    /// Maps destination address to indices into `next`. If there are multiple,
+    /// they are equal-cost paths and each flow is hashed onto one of them
+    table: HashMap<u16, Vec<usize>>,
+    /// Where to send packets whose address is not in `table`
+    default_route: Option<usize>,
+    next: Vec<Rc<RefCell<N>>>,
+    /// `(tick, addr, index into next)` route changes that haven't happened
+    /// yet, in increasing order of tick
+    pending: VecDeque<(u64, u16, usize)>,
+    tick_num: u64,
+    /// Used to drop packets we don't know how to route
+    dropper: Transport<Sink>,
+}
+
+impl<N: Element + ?Sized> Router<N> {
+    pub fn new(coord: Coord) -> Self {
+        let dropper = Transport::new(
+            16,
+            Rc::new(RefCell::new(Sink {
+                coord: coord.sub(Coord(DATA_PKT_WIDTH, 5.)),
+            })),
+        );
+
+        Self {
+            coord,
+            table: HashMap::new(),
+            default_route: None,
+            next: Vec::new(),
+            pending: VecDeque::new(),
+            tick_num: 0,
+            dropper,
+        }
+    }
+
+    /// Send packets with address `addr` to `next`. Adding multiple routes for
+    /// the same address creates equal-cost paths (ECMP)
+    pub fn add_route(&mut self, addr: u16, next: Rc<RefCell<N>>) {
+        let i = self.next_index(next);
+        self.table.entry(addr).or_default().push(i);
+    }
+
+    /// Send packets that match no route to `next`
+    pub fn set_default_route(&mut self, next: Rc<RefCell<N>>) {
+        self.default_route = Some(self.next_index(next));
+    }
+
+    /// From tick `at` on, send packets with address `addr` only to `next`,
+    /// replacing whatever routes it had. Use this to animate route flaps
+    pub fn schedule_route(&mut self, at: u64, addr: u16, next: Rc<RefCell<N>>) {
+        let i = self.next_index(next);
+        let pos = self.pending.partition_point(|(tick, ..)| *tick <= at);
+        self.pending.insert(pos, (at, addr, i));
+    }
+
+    /// Index of `next` in `self.next`, adding it if it isn't there yet
+    fn next_index(&mut self, next: Rc<RefCell<N>>) -> usize {
+        if let Some(i) = self.next.iter().position(|n| Rc::ptr_eq(n, &next)) {
+            return i;
+        }
+        self.next.push(next);
+        self.next.len() - 1
+    }
+
+    /// Which element in `next` the packet should go to, if any
+    fn lookup(&self, pkt: &Packet) -> Option<usize> {
+        match self.table.get(&pkt.addr) {
+            Some(routes) if !routes.is_empty() => {
+                // All packets of a flow take the same path
+                Some(routes[flow_hash(pkt.flow, pkt.addr) as usize % routes.len()])
+            }
+            _ => self.default_route,
+        }
+    }
+}
+
+/// FNV-1a hash of a flow and address. Unlike std's hashers, it is fixed, so
+/// animations don't change which path a flow takes on a toolchain update
+fn flow_hash(flow: u16, addr: u16) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in flow.to_le_bytes().into_iter().chain(addr.to_le_bytes()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+impl<N: Element + ?Sized> Element for Router<N> {
+    fn get_enqueue_coord(&self) -> Coord {
+        self.coord
+    }
+
+    fn enqueue(&mut self, pkt: &Packet) {
+        let mut pkt = *pkt;
+        pkt.coord = self.coord;
+        match self.lookup(&pkt) {
+            Some(i) => self.next[i].borrow_mut().enqueue(&pkt),
+            None => {
+                check::dropped(&pkt);
+                self.dropper.enqueue(&pkt);
+            }
+        }
+    }
+
+    fn get_pkts(&self) -> Vec<Packet> {
+        Vec::new()
+    }
+
+    fn tick(&mut self) {
+        while let Some((_, addr, i)) = self
+            .pending
+            .front()
+            .filter(|(tick, ..)| *tick <= self.tick_num)
+            .copied()
+        {
+            self.pending.pop_front();
+            self.table.insert(addr, vec![i]);
+        }
+        self.dropper.tick();
+        self.tick_num += 1;
+    }
+
+    /// Shows the packets we couldn't route on their way to being dropped
+    fn draw(&self, theme: &Theme) -> Vec<Shape> {
+        self.dropper
+            .get_pkts()
+            .iter()
+            .flat_map(|pkt| pkt.draw_dropped(theme))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::{Color, Style};
+    use crate::simple_elems::Recorder;
+
+    fn recorder() -> Rc<RefCell<Recorder>> {
+        Rc::new(RefCell::new(Recorder::new(Coord(10., 0.))))
+    }
+
+    fn pkt(addr: u16, flow: u16) -> Packet {
+        Packet::new(
+            DATA_PKT_WIDTH,
+            Coord(-10., 0.),
+            addr,
+            flow,
+            Style::from(Color::RED).filled(),
+        )
+    }
+
+    /// Flows of the packets `recorder` received, in order
+    fn flows(recorder: &Rc<RefCell<Recorder>>) -> Vec<u16> {
+        recorder
+            .borrow()
+            .received()
+            .iter()
+            .map(|r| r.1.flow)
+            .collect()
+    }
+
+    #[test]
+    fn routes_by_address() {
+        let (a, b, c) = (recorder(), recorder(), recorder());
+        let mut router = Router::new(Coord(0., 0.));
+        router.add_route(1, a.clone());
+        router.add_route(2, b.clone());
+        router.set_default_route(c.clone());
+        for (addr, flow) in [(1, 10), (2, 20), (7, 70), (1, 11)] {
+            router.enqueue(&pkt(addr, flow));
+        }
+        assert_eq!(flows(&a), vec![10, 11]);
+        assert_eq!(flows(&b), vec![20]);
+        // Nothing else matches address 7
+        assert_eq!(flows(&c), vec![70]);
+        // Packets leave from the router
+        assert_eq!(a.borrow().received()[0].1.coord.0, 0.);
+    }
+
+    #[test]
+    fn hashes_flows_onto_equal_cost_paths() {
+        let paths = [recorder(), recorder(), recorder()];
+        let mut router = Router::new(Coord(0., 0.));
+        for path in &paths {
+            router.add_route(0, path.clone());
+        }
+        for _ in 0..3 {
+            for flow in 0..30 {
+                router.enqueue(&pkt(0, flow));
+            }
+        }
+        for (i, path) in paths.iter().enumerate() {
+            // Every packet of a flow takes the path its hash picks
+            let flows = flows(path);
+            assert!(!flows.is_empty());
+            assert!(flows
+                .iter()
+                .all(|flow| flow_hash(*flow, 0) as usize % paths.len() == i));
+        }
+    }
+
+    #[test]
+    fn drops_unroutable_packets() {
+        check::enable();
+        let a = recorder();
+        let mut router = Router::new(Coord(0., 0.));
+        router.add_route(1, a.clone());
+        let mut lost = pkt(2, 20);
+        check::created(&mut lost);
+        router.enqueue(&lost);
+        assert!(a.borrow().received().is_empty());
+        assert_eq!(router.dropper.get_pkts().len(), 1);
+        assert_eq!(check::finish(Vec::new()).unwrap().num_dropped, 1);
+        // They are drawn in the theme's drop color
+        let theme = Theme::colorblind();
+        let drop_color = theme.drop.unwrap();
+        assert!(router.draw(&theme).iter().any(|shape| matches!(
+            shape,
+            Shape::Rect { style, .. } if style.color == drop_color
+        )));
+    }
+
+    #[test]
+    fn flaps_between_next_hops() {
+        let (a, b) = (recorder(), recorder());
+        let mut router = Router::new(Coord(0., 0.));
+        router.add_route(0, a.clone());
+        for at in 1..10 {
+            let next = if at % 2 == 0 { a.clone() } else { b.clone() };
+            router.schedule_route(at, 0, next);
+        }
+        for tick in 0..10 {
+            router.tick();
+            router.enqueue(&pkt(0, tick));
+        }
+        assert_eq!(flows(&a), vec![0, 2, 4, 6, 8]);
+        assert_eq!(flows(&b), vec![1, 3, 5, 7, 9]);
+        // Flapping back and forth doesn't pile up next hops
+        assert_eq!(router.next.len(), 2);
+    }
+
+    #[test]
+    fn flow_hash_is_fnv1a() {
+        assert_eq!(flow_hash(0, 0), 0x4d25767f9dce13f5);
+        assert_ne!(flow_hash(1, 0), flow_hash(0, 1));
+    }
+}
@@ -0,0 +1,188 @@
+//! Small simulations that return a curve to plot, for sweeping one of their
+//! parameters with `sweep_anim`
+use crate::ack_path::{AckChannel, AckPath};
+use crate::base::{Coord, Element, Packet, DATA_PKT_WIDTH};
+use crate::bottleneck::Bottleneck;
+use crate::scene::{Color, Style};
+use crate::sender::{EcnResponse, WindowSender};
+use crate::simple_elems::Acker;
+use crate::simulation::Simulation;
+use crate::transport::Transport;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A DCTCP flow through a bottleneck that marks packets arriving to a long
+/// queue
+#[derive(Clone, Debug)]
+pub struct DctcpScenario {
+    /// Round trip propagation delay, in the units `Transport::new` takes
+    pub rtt: u64,
+    /// Buffer size of the bottleneck. Packets beyond it are dropped
+    pub bufsize: u64,
+    /// Mark packets that arrive to find at least this many packets queued
+    pub ecn_threshold: u64,
+    /// Bottleneck link rate
+    pub bottleneck_intersend: u64,
+    /// Number of ticks to simulate
+    pub num_ticks: u64,
+}
+
+impl Default for DctcpScenario {
+    fn default() -> Self {
+        Self {
+            rtt: 40,
+            bufsize: 64,
+            ecn_threshold: 8,
+            bottleneck_intersend: 8,
+            num_ticks: 2000,
+        }
+    }
+}
+
+impl DctcpScenario {
+    /// Length of the bottleneck queue on every tick
+    pub fn queue(&self) -> Vec<(f64, f64)> {
+        let arrival = self.rtt * 2 / 5;
+        let departure = self.rtt / 5;
+        let AckedFlowSim {
+            mut sim,
+            bottleneck,
+            ..
+        } = AckedFlow {
+            delays: (arrival, departure, self.rtt - arrival - departure),
+            bufsize: self.bufsize,
+            bottleneck_intersend: self.bottleneck_intersend,
+            response: EcnResponse::Dctcp { gain: 1. / 16. },
+            init_cwnd: 8.,
+            ack_channel: None,
+        }
+        .build();
+        bottleneck
+            .borrow_mut()
+            .set_ecn_threshold(Some(self.ecn_threshold));
+        (0..self.num_ticks)
+            .map(|tick| {
+                sim.tick();
+                (tick as f64, bottleneck.borrow().get_pkts().len() as f64)
+            })
+            .collect()
+    }
+}
+
+/// A `WindowSender` whose packets cross a bottleneck to a receiver that acks
+/// them back to the sender. The scenarios and the tests of the elements
+/// involved all simulate this
+pub struct AckedFlow {
+    /// Delays from the sender to the bottleneck, from the bottleneck to the
+    /// receiver and from the receiver back to the sender
+    pub delays: (u64, u64, u64),
+    pub bufsize: u64,
+    pub bottleneck_intersend: u64,
+    pub response: EcnResponse,
+    pub init_cwnd: f64,
+    /// Acks cross an `AckPath` with this channel on their way back, if given
+    pub ack_channel: Option<AckChannel>,
+}
+
+/// The simulation an `AckedFlow` builds, with the elements we watch
+pub struct AckedFlowSim {
+    pub sim: Simulation,
+    pub sender: Rc<RefCell<WindowSender<dyn Element>>>,
+    pub bottleneck: Rc<RefCell<Bottleneck<dyn Element>>>,
+}
+
+impl AckedFlow {
+    pub fn build(&self) -> AckedFlowSim {
+        let (arrival, departure, returnpath) = self.delays;
+        // We will populate `next` once the return path exists
+        let bottleneck = Rc::new(RefCell::new(Bottleneck::<dyn Element>::new(
+            Coord(0., 0.),
+            self.bufsize,
+            vec![self.bottleneck_intersend],
+            vec![],
+            true,
+        )));
+        let arrival = Rc::new(RefCell::new(Transport::new(arrival, bottleneck.clone())));
+        let template = Packet::new(
+            DATA_PKT_WIDTH,
+            Coord(-10., 0.),
+            0,
+            0,
+            Style::from(Color::RED).filled(),
+        );
+        let sender: Rc<RefCell<WindowSender<dyn Element>>> =
+            Rc::new(RefCell::new(WindowSender::new(
+                template,
+                Coord(-10., -2.),
+                arrival.clone(),
+                self.response,
+                self.init_cwnd,
+            )));
+        let ack_path: Option<Rc<RefCell<dyn Element>>> = self.ack_channel.clone().map(|channel| {
+            Rc::new(RefCell::new(AckPath::new(
+                Coord(-8., -2.),
+                channel,
+                sender.clone(),
+            ))) as Rc<RefCell<dyn Element>>
+        });
+        let returnpath = Rc::new(RefCell::new(Transport::new(
+            returnpath,
+            ack_path.clone().unwrap_or(sender.clone()),
+        )));
+        let acker = Rc::new(RefCell::new(Acker {
+            rcv_coord: Coord(10., 0.),
+            snd_coord: Coord(10., -2.),
+            next: returnpath.clone(),
+        }));
+        let departure = Rc::new(RefCell::new(Transport::new(departure, acker.clone())));
+        bottleneck.borrow_mut().set_next(vec![departure.clone()]);
+
+        let mut sim = Simulation::new();
+        sim.add("sender", sender.clone());
+        sim.add("arrival", arrival);
+        sim.add("bottleneck", bottleneck.clone());
+        sim.add("departure", departure);
+        sim.add("acker", acker);
+        sim.add("returnpath", returnpath);
+        if let Some(ack_path) = ack_path {
+            sim.add("ack_path", ack_path);
+        }
+        AckedFlowSim {
+            sim,
+            sender,
+            bottleneck,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn max_queue(scenario: DctcpScenario) -> f64 {
+        scenario.queue().iter().map(|(_, q)| *q).fold(0., f64::max)
+    }
+
+    fn mean_queue(scenario: DctcpScenario) -> f64 {
+        let queue = scenario.queue();
+        queue.iter().map(|(_, q)| *q).sum::<f64>() / queue.len() as f64
+    }
+
+    #[test]
+    fn parameters_reach_the_simulation() {
+        // The initial window of 8 packets overflows a 4 packet buffer
+        assert!(max_queue(DctcpScenario::default()) > 4.);
+        let small = DctcpScenario {
+            bufsize: 4,
+            ..Default::default()
+        };
+        assert!(max_queue(small) <= 4.);
+        // With a short RTT, the window exceeds what the path holds and the
+        // rest queues
+        let short = DctcpScenario {
+            rtt: 10,
+            ..Default::default()
+        };
+        assert!(mean_queue(short) > mean_queue(DctcpScenario::default()) + 1.);
+    }
+}
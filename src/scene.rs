@@ -0,0 +1,263 @@
+//! Backend-independent description of a frame. Elements describe what they
+//! look like as owned shapes, and `render` draws those with plotters
+use crate::annotation::FONT_SIZE;
+use crate::base::Coord;
+use plotters::coord::types::RangedCoordf64;
+use plotters::prelude::*;
+use plotters::style::text_anchor::{HPos, Pos, VPos};
+use plotters::style::Color as _;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Color(pub u8, pub u8, pub u8);
+
+impl Color {
+    pub const BLACK: Color = Color(0, 0, 0);
+    pub const WHITE: Color = Color(255, 255, 255);
+    pub const RED: Color = Color(255, 0, 0);
+    pub const GREEN: Color = Color(0, 255, 0);
+    pub const BLUE: Color = Color(0, 0, 255);
+    pub const MAGENTA: Color = Color(255, 0, 255);
+
+    /// Fill in this color, with no outline
+    pub fn filled(self) -> Style {
+        Style {
+            color: self,
+            filled: true,
+            stroke_width: 0,
+        }
+    }
+}
+
+impl From<Color> for RGBColor {
+    fn from(color: Color) -> RGBColor {
+        RGBColor(color.0, color.1, color.2)
+    }
+}
+
+/// How to draw the lines or the inside of a shape
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Style {
+    pub color: Color,
+    /// Fill the shape instead of outlining it
+    pub filled: bool,
+    /// Width of lines and outlines, in pixels
+    pub stroke_width: u32,
+}
+
+impl Style {
+    pub fn filled(self) -> Self {
+        Self {
+            filled: true,
+            ..self
+        }
+    }
+
+    pub fn stroke_width(self, stroke_width: u32) -> Self {
+        Self {
+            stroke_width,
+            ..self
+        }
+    }
+}
+
+impl From<Style> for ShapeStyle {
+    fn from(style: Style) -> ShapeStyle {
+        ShapeStyle {
+            color: RGBColor::from(style.color).to_rgba(),
+            filled: style.filled,
+            stroke_width: style.stroke_width,
+        }
+    }
+}
+
+/// Lines a pixel wide
+impl From<Color> for Style {
+    fn from(color: Color) -> Self {
+        Self {
+            color,
+            filled: false,
+            stroke_width: 1,
+        }
+    }
+}
+
+/// Which part of a line of text lines up with its coordinate
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// Something to draw. Positions are in chart coordinates unless noted
+#[derive(Clone)]
+pub enum Shape {
+    /// Axis-aligned rectangle between two opposite corners
+    Rect {
+        corners: (Coord, Coord),
+        style: Style,
+    },
+    /// Open polyline through `points`
+    Path { points: Vec<Coord>, style: Style },
+    /// Closed area bounded by `points`
+    Polygon { points: Vec<Coord>, style: Style },
+    /// Circle whose radius is in pixels, so it looks the same at any zoom
+    Circle {
+        center: Coord,
+        radius: i32,
+        style: Style,
+    },
+    /// Rectangle whose corners are pixel offsets from `at`
+    IconRect {
+        at: Coord,
+        corners: ((i32, i32), (i32, i32)),
+        style: Style,
+    },
+    /// Polyline whose points are pixel offsets from `at`
+    IconPath {
+        at: Coord,
+        points: Vec<(i32, i32)>,
+        style: Style,
+    },
+    /// A line of text, vertically centered on `coord`
+    Text {
+        text: String,
+        coord: Coord,
+        /// Which part of the text lines up with `coord`
+        align: Align,
+        color: Color,
+    },
+}
+
+impl Shape {
+    /// Lower-left and upper-right corners of a box around the shape, in chart
+    /// coordinates. `units_per_px` converts the parts measured in pixels
+    pub fn bounds(&self, units_per_px: (f64, f64)) -> (Coord, Coord) {
+        // Pixel offsets point down the y-axis
+        let offset = |at: &Coord, (x, y): (i32, i32)| {
+            Coord(
+                at.0 + x as f64 * units_per_px.0,
+                at.1 - y as f64 * units_per_px.1,
+            )
+        };
+        let points = match self {
+            Shape::Rect { corners, .. } => vec![corners.0, corners.1],
+            Shape::Path { points, .. } | Shape::Polygon { points, .. } => points.clone(),
+            Shape::Circle { center, radius, .. } => {
+                vec![
+                    offset(center, (-radius, -radius)),
+                    offset(center, (*radius, *radius)),
+                ]
+            }
+            Shape::IconRect { at, corners, .. } => {
+                vec![offset(at, corners.0), offset(at, corners.1)]
+            }
+            Shape::IconPath { at, points, .. } => points.iter().map(|p| offset(at, *p)).collect(),
+            Shape::Text {
+                text, coord, align, ..
+            } => {
+                let (w, h) = ("sans-serif", FONT_SIZE)
+                    .into_font()
+                    .box_size(text)
+                    .unwrap_or((0, 0));
+                let (w, h) = (w as i32, h as i32);
+                let left = match align {
+                    Align::Left => 0,
+                    Align::Center => -w / 2,
+                    Align::Right => -w,
+                };
+                vec![
+                    offset(coord, (left, -h / 2)),
+                    offset(coord, (left + w, h / 2)),
+                ]
+            }
+        };
+        let mut lo = Coord(f64::INFINITY, f64::INFINITY);
+        let mut hi = Coord(f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for p in points {
+            lo = Coord(lo.0.min(p.0), lo.1.min(p.1));
+            hi = Coord(hi.0.max(p.0), hi.1.max(p.1));
+        }
+        (lo, hi)
+    }
+}
+
+/// Draw `shapes` in order, so later ones appear on top. Everything measured in
+/// pixels (line widths, radii, icons and text) is multiplied by `scale`, so a
+/// canvas with `scale` times the pixels looks the same, only sharper
+pub fn render<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Cartesian2d<RangedCoordf64, RangedCoordf64>>,
+    shapes: &[Shape],
+    scale: f64,
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    let px = |v: i32| (v as f64 * scale).round() as i32;
+    let pt = |(x, y): (i32, i32)| (px(x), px(y));
+    let style = |style: &Style| ShapeStyle {
+        stroke_width: px(style.stroke_width as i32) as u32,
+        ..ShapeStyle::from(*style)
+    };
+    for shape in shapes {
+        match shape {
+            Shape::Rect { corners, style: s } => area.draw(&Rectangle::new(
+                [corners.0.into(), corners.1.into()],
+                style(s),
+            ))?,
+            Shape::Path { points, style: s } => area.draw(&PathElement::new(
+                points
+                    .iter()
+                    .map(|c| (*c).into())
+                    .collect::<Vec<(f64, f64)>>(),
+                style(s),
+            ))?,
+            Shape::Polygon { points, style: s } => area.draw(&Polygon::new(
+                points
+                    .iter()
+                    .map(|c| (*c).into())
+                    .collect::<Vec<(f64, f64)>>(),
+                style(s),
+            ))?,
+            Shape::Circle {
+                center,
+                radius,
+                style: s,
+            } => area.draw(&Circle::new((*center).into(), px(*radius), style(s)))?,
+            Shape::IconRect {
+                at,
+                corners,
+                style: s,
+            } => area.draw(
+                &(EmptyElement::at((*at).into())
+                    + Rectangle::new([pt(corners.0), pt(corners.1)], style(s))),
+            )?,
+            Shape::IconPath {
+                at,
+                points,
+                style: s,
+            } => area.draw(
+                &(EmptyElement::at((*at).into())
+                    + PathElement::new(
+                        points.iter().map(|p| pt(*p)).collect::<Vec<_>>(),
+                        style(s),
+                    )),
+            )?,
+            Shape::Text {
+                text,
+                coord,
+                align,
+                color,
+            } => {
+                let align = match align {
+                    Align::Left => HPos::Left,
+                    Align::Center => HPos::Center,
+                    Align::Right => HPos::Right,
+                };
+                let font = ("sans-serif", FONT_SIZE * scale)
+                    .into_font()
+                    .color(&RGBColor::from(*color))
+                    .pos(Pos::new(align, VPos::Center));
+                area.draw(&Text::new(text.as_str(), (*coord).into(), font))?
+            }
+        }
+    }
+    Ok(())
+}
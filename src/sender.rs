@@ -0,0 +1,470 @@
+//! Senders that react to feedback carried on acks
+use crate::base::{Coord, Element, Packet, PKT_HEIGHT};
+use crate::check;
+use crate::scene::{Align, Shape};
+use crate::theme::Theme;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+/// Spaces packets out at a rate given in packets per tick. The rate need not
+/// be a whole number of packets per tick: whatever we could not send in one
+/// tick carries over to the next
+#[derive(Default)]
+pub struct Pacer {
+    /// Number of packets we are allowed to send right now
+    credit: f64,
+}
+
+impl Pacer {
+    pub fn new() -> Self {
+        Self { credit: 0. }
+    }
+
+    /// Accrue a tick's worth of credit
+    pub fn tick(&mut self, rate: f64) {
+        self.credit += rate;
+    }
+
+    /// Use up a packet's worth of credit if we have it
+    pub fn take(&mut self) -> bool {
+        if self.credit >= 1. {
+            self.credit -= 1.;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Call when there was nothing to send, so credit saved up while idle
+    /// doesn't turn into a burst later
+    pub fn idle(&mut self) {
+        self.credit = f64::min(1., self.credit);
+    }
+}
+
+/// How a `WindowSender` responds to acks that echo a CE mark
+#[derive(Clone, Copy, Debug)]
+pub enum EcnResponse {
+    /// Ignore marks
+    Ignore,
+    /// Halve the window at most once per window of data, like a loss (RFC
+    /// 3168)
+    Classic,
+    /// Once per window of data, cut the window in proportion to a running
+    /// estimate of the fraction of packets marked, as in DCTCP. `gain` is the
+    /// weight of the latest window in that estimate (1/16 in the paper)
+    Dctcp { gain: f64 },
+}
+
+/// A packet counts as lost once a packet sent this many after it is acked
+const REORDER_THRESHOLD: u64 = 3;
+/// Retransmission timeout in ticks until we measure the RTT
+const INIT_RTO: f64 = 200.;
+
+/// Window-based sender. It keeps at most `cwnd` packets in flight, grows
+/// `cwnd` by one packet per window of acks and shrinks it on ECN marks as
+/// given by its `EcnResponse`. It is also the sink for its own acks. A packet
+/// is lost if a later one is acked or it times out, which halves `cwnd` and
+/// frees its slot in the window. There are no retransmissions. By default it
+/// sends as soon as the window allows, but it can also pace packets at
+/// `cwnd / srtt`
+pub struct WindowSender<N: Element + ?Sized> {
+    /// Every packet we send is a copy of this
+    template: Packet,
+    /// Where acks arrive
+    ack_coord: Coord,
+    next: Rc<RefCell<N>>,
+    response: EcnResponse,
+    cwnd: f64,
+    /// Sequence number of the next packet we send
+    next_seq: u64,
+    /// After a cut, ignore marks and losses of packets numbered below this,
+    /// since they come from the window in which we cut
+    recover: u64,
+    /// DCTCP's current window of data ends with the ack of the packet
+    /// numbered just below this
+    window_end: u64,
+    /// Number of acks and marked acks in DCTCP's current window
+    window_acked: u64,
+    window_marked: u64,
+    /// DCTCP's estimate of the fraction of packets marked
+    alpha: f64,
+    /// If pacing, we send at this multiple of `cwnd / srtt`
+    pacing_gain: Option<f64>,
+    /// RTT to pace with until we measure one
+    init_rtt: f64,
+    pacer: Pacer,
+    /// Smoothed RTT in ticks
+    srtt: Option<f64>,
+    /// Smoothed deviation of the RTT, which pads the timeout
+    rttvar: f64,
+    /// Tick at which each packet in flight was sent, by sequence number
+    send_times: BTreeMap<u64, u64>,
+    tick_num: u64,
+}
+
+impl<N: Element + ?Sized> WindowSender<N> {
+    pub fn new(
+        template: Packet,
+        ack_coord: Coord,
+        next: Rc<RefCell<N>>,
+        response: EcnResponse,
+        init_cwnd: f64,
+    ) -> Self {
+        Self {
+            template,
+            ack_coord,
+            next,
+            response,
+            cwnd: init_cwnd,
+            next_seq: 0,
+            recover: 0,
+            window_end: 0,
+            window_acked: 0,
+            window_marked: 0,
+            alpha: 0.,
+            pacing_gain: None,
+            init_rtt: 0.,
+            pacer: Pacer::new(),
+            srtt: None,
+            rttvar: 0.,
+            send_times: BTreeMap::new(),
+            tick_num: 0,
+        }
+    }
+
+    /// Pace packets at `gain * cwnd / srtt` instead of sending them in a
+    /// burst whenever the window opens. Until we measure it, assume the RTT
+    /// is `init_rtt` ticks
+    pub fn set_pacing(&mut self, gain: f64, init_rtt: u64) {
+        self.pacing_gain = Some(gain);
+        self.init_rtt = init_rtt as f64;
+    }
+
+    /// Packets per tick we pace at, if pacing
+    fn pacing_rate(&self) -> Option<f64> {
+        let srtt = f64::max(1., self.srtt.unwrap_or(self.init_rtt));
+        Some(self.pacing_gain? * self.cwnd / srtt)
+    }
+
+    /// Congestion window in packets
+    pub fn cwnd(&self) -> f64 {
+        self.cwnd
+    }
+
+    /// Number of packets sent but neither acked nor lost
+    pub fn in_flight(&self) -> usize {
+        self.send_times.len()
+    }
+
+    /// Halve the window, at most once per window of data
+    fn cut(&mut self, seq: u64) {
+        if seq >= self.recover {
+            self.cwnd = f64::max(1., self.cwnd / 2.);
+            self.recover = self.next_seq;
+        }
+    }
+
+    /// Forget packets sent before `seq`, which were lost, or those sent
+    /// longer than a timeout ago if `seq` is `None`
+    fn detect_losses(&mut self, seq: Option<u64>) {
+        let lost: Vec<u64> = match seq {
+            Some(seq) => self
+                .send_times
+                .range(..seq.saturating_sub(REORDER_THRESHOLD - 1))
+                .map(|(seq, _)| *seq)
+                .collect(),
+            None => {
+                let rto = self.srtt.map_or(INIT_RTO, |srtt| {
+                    f64::max(2. * srtt, srtt + 4. * self.rttvar)
+                });
+                self.send_times
+                    .iter()
+                    .filter(|(_, sent)| (self.tick_num - **sent) as f64 > rto)
+                    .map(|(seq, _)| *seq)
+                    .collect()
+            }
+        };
+        for seq in lost {
+            self.send_times.remove(&seq);
+            self.cut(seq);
+        }
+    }
+
+    /// Called at the end of every window of data for DCTCP
+    fn end_window(&mut self, gain: f64) {
+        let frac = self.window_marked as f64 / self.window_acked as f64;
+        self.alpha = (1. - gain) * self.alpha + gain * frac;
+        if self.window_marked > 0 {
+            self.cwnd *= 1. - self.alpha / 2.;
+        }
+        self.window_end = self.next_seq;
+        self.window_acked = 0;
+        self.window_marked = 0;
+    }
+}
+
+impl<N: Element + ?Sized> Element for WindowSender<N> {
+    fn get_enqueue_coord(&self) -> Coord {
+        self.ack_coord
+    }
+
+    /// Receive an ack
+    fn enqueue(&mut self, pkt: &Packet) {
+        check::delivered(pkt);
+        let Some(sent) = self.send_times.remove(&pkt.seq) else {
+            // We already gave up on it
+            return;
+        };
+        // Acks thinned out on the way were for the packets just before this
+        // one. Older packets still in flight are losses, not thinned acks
+        let covers = u64::max(1, pkt.covers);
+        let thinned: Vec<u64> = self
+            .send_times
+            .range(..pkt.seq)
+            .rev()
+            .take(covers as usize - 1)
+            .map(|(seq, _)| *seq)
+            .collect();
+        for seq in thinned {
+            self.send_times.remove(&seq);
+        }
+        let rtt = (self.tick_num - sent) as f64;
+        // As in RFC 6298
+        self.srtt = Some(match self.srtt {
+            Some(srtt) => {
+                self.rttvar = 0.75 * self.rttvar + 0.25 * (srtt - rtt).abs();
+                0.875 * srtt + 0.125 * rtt
+            }
+            None => {
+                self.rttvar = rtt / 2.;
+                rtt
+            }
+        });
+        self.detect_losses(Some(pkt.seq));
+        self.window_acked += covers;
+        if pkt.ce {
+            self.window_marked += covers;
+        }
+
+        match self.response {
+            EcnResponse::Classic if pkt.ce => self.cut(pkt.seq),
+            EcnResponse::Dctcp { gain } => {
+                self.cwnd += covers as f64 / self.cwnd;
+                if pkt.seq + 1 >= self.window_end {
+                    self.end_window(gain);
+                }
+            }
+            _ => self.cwnd += covers as f64 / self.cwnd,
+        }
+        self.cwnd = f64::max(1., self.cwnd);
+    }
+
+    fn get_pkts(&self) -> Vec<Packet> {
+        Vec::new()
+    }
+
+    fn tick(&mut self) {
+        self.detect_losses(None);
+        let rate = self.pacing_rate();
+        if let Some(rate) = rate {
+            self.pacer.tick(rate);
+        }
+        loop {
+            if self.in_flight() as f64 >= self.cwnd.floor() {
+                // Window limited
+                self.pacer.idle();
+                break;
+            }
+            if rate.is_some() && !self.pacer.take() {
+                break;
+            }
+            let mut pkt = self.template;
+            pkt.seq = self.next_seq;
+            check::created(&mut pkt);
+            self.next.borrow_mut().enqueue(&pkt);
+            self.send_times.insert(self.next_seq, self.tick_num);
+            self.next_seq += 1;
+        }
+        self.tick_num += 1;
+    }
+
+    /// Shows the current window just below the point where packets start
+    fn draw(&self, theme: &Theme) -> Vec<Shape> {
+        vec![theme.text(
+            format!("cwnd = {:.1}", self.cwnd),
+            self.template.coord.add(Coord(1., -PKT_HEIGHT)),
+            Align::Left,
+        )]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::{AckedFlow, AckedFlowSim};
+    use crate::scene::{Color, Style};
+    use crate::simple_elems::Recorder;
+
+    fn sender(response: EcnResponse, init_cwnd: f64) -> WindowSender<Recorder> {
+        let template = Packet::new(
+            0.25,
+            Coord(-10., 0.),
+            0,
+            0,
+            Style::from(Color::RED).filled(),
+        );
+        let recorder = Rc::new(RefCell::new(Recorder::new(Coord(0., 0.))));
+        WindowSender::new(template, Coord(-10., -2.), recorder, response, init_cwnd)
+    }
+
+    /// Ack the packet numbered `seq`, echoing `ce`
+    fn ack(sender: &mut WindowSender<Recorder>, seq: u64, ce: bool) {
+        let mut pkt = sender.template;
+        pkt.ack = true;
+        pkt.seq = seq;
+        pkt.ce = ce;
+        pkt.covers = 1;
+        sender.enqueue(&pkt);
+    }
+
+    #[test]
+    fn paces_at_the_rate() {
+        // 8 packets per 16 ticks
+        let mut sender = sender(EcnResponse::Ignore, 8.);
+        sender.set_pacing(1., 16);
+        let mut sent_at = Vec::new();
+        for tick in 0..16 {
+            let in_flight = sender.in_flight();
+            sender.tick();
+            if sender.in_flight() > in_flight {
+                assert_eq!(sender.in_flight(), in_flight + 1);
+                sent_at.push(tick);
+            }
+        }
+        assert_eq!(sent_at, vec![1, 3, 5, 7, 9, 11, 13, 15]);
+    }
+
+    #[test]
+    fn window_limited_sender_saves_no_credit() {
+        let mut sender = sender(EcnResponse::Ignore, 2.);
+        sender.set_pacing(1., 4);
+        for _ in 0..40 {
+            sender.tick();
+        }
+        assert_eq!(sender.in_flight(), 2);
+        ack(&mut sender, 0, false);
+        ack(&mut sender, 1, false);
+        assert!(sender.cwnd() >= 2.);
+        // The window has room for two, but we only saved up one
+        sender.tick();
+        assert_eq!(sender.in_flight(), 1);
+    }
+
+    #[test]
+    fn paces_with_the_measured_rtt() {
+        let mut sender = sender(EcnResponse::Ignore, 8.);
+        sender.set_pacing(2., 100);
+        assert_eq!(sender.pacing_rate(), Some(2. * 8. / 100.));
+        while sender.in_flight() == 0 {
+            sender.tick();
+        }
+        for _ in 0..10 {
+            sender.tick();
+        }
+        let rtt = (sender.tick_num - sender.send_times[&0]) as f64;
+        ack(&mut sender, 0, false);
+        // The guess doesn't count as a sample
+        assert_eq!(sender.srtt, Some(rtt));
+        assert_eq!(sender.rttvar, rtt / 2.);
+        assert_eq!(sender.pacing_rate(), Some(2. * sender.cwnd() / rtt));
+    }
+
+    #[test]
+    fn classic_cuts_once_per_window() {
+        let mut sender = sender(EcnResponse::Classic, 8.);
+        sender.tick();
+        assert_eq!(sender.in_flight(), 8);
+        ack(&mut sender, 0, true);
+        assert_eq!(sender.cwnd(), 4.);
+        // The rest of the window was sent before we cut
+        ack(&mut sender, 1, true);
+        assert_eq!(sender.cwnd(), 4.);
+        for seq in 2..8 {
+            ack(&mut sender, seq, false);
+        }
+        let cwnd = sender.cwnd();
+        assert!(cwnd > 4.);
+        // Marks on packets sent after the cut count again
+        sender.tick();
+        ack(&mut sender, 8, true);
+        assert_eq!(sender.cwnd(), cwnd / 2.);
+    }
+
+    #[test]
+    fn dctcp_cuts_in_proportion_to_marks() {
+        // Acks of packets 1 to 7 make up the second window, of which
+        // `num_marked` are marked
+        let second_window = |num_marked: u64| {
+            let mut sender = sender(EcnResponse::Dctcp { gain: 0.5 }, 8.);
+            sender.tick();
+            // The first window ends with the first ack
+            ack(&mut sender, 0, false);
+            assert_eq!(sender.alpha, 0.);
+            let mut cwnd = sender.cwnd();
+            for seq in 1..8 {
+                cwnd += 1. / cwnd;
+                ack(&mut sender, seq, seq <= num_marked);
+            }
+            (cwnd, sender.alpha, sender.cwnd())
+        };
+
+        let (cwnd, alpha, after) = second_window(0);
+        assert_eq!(alpha, 0.);
+        assert_eq!(after, cwnd);
+
+        let (cwnd, alpha, after) = second_window(7);
+        assert_eq!(alpha, 0.5);
+        assert_eq!(after, cwnd * 0.75);
+
+        let (cwnd, alpha, after) = second_window(2);
+        assert_eq!(alpha, 0.5 * 2. / 7.);
+        assert_eq!(after, cwnd * (1. - alpha / 2.));
+    }
+
+    #[test]
+    fn recovers_from_tail_drops() {
+        const BUFSIZE: u64 = 4;
+        let AckedFlowSim {
+            mut sim, sender, ..
+        } = AckedFlow {
+            delays: (8, 4, 8),
+            bufsize: BUFSIZE,
+            bottleneck_intersend: 8,
+            response: EcnResponse::Ignore,
+            init_cwnd: 2.,
+            ack_channel: None,
+        }
+        .build();
+
+        // cwnd grows until the buffer overflows, which must cut it, and then
+        // grows again
+        let mut cwnds = Vec::new();
+        for _ in 0..6000 {
+            sim.tick();
+            cwnds.push(sender.borrow().cwnd());
+        }
+        let cuts: Vec<usize> = (1..cwnds.len())
+            .filter(|i| cwnds[*i] < cwnds[*i - 1])
+            .collect();
+        assert!(cuts.len() >= 2, "the buffer never overflowed");
+        let last_cut = *cuts.last().unwrap();
+        assert!(cwnds[last_cut..].iter().any(|cwnd| *cwnd > cwnds[last_cut]));
+        // A window's worth of packets fits in the pipe and the buffer, so
+        // lost packets don't linger in flight
+        let sender = sender.borrow();
+        assert!(sender.in_flight() as f64 <= sender.cwnd().ceil());
+        assert!(sender.cwnd() < 2. * (BUFSIZE as f64 + 10.));
+    }
+}
@@ -1,5 +1,7 @@
 use crate::base::{Coord, Element, Packet, ACK_PKT_WIDTH};
-use plotters::prelude::*;
+use crate::check;
+use crate::scene::{Align, Shape};
+use crate::theme::Theme;
 use std::cell::RefCell;
 use std::rc::Rc;
 
@@ -12,30 +14,143 @@ impl Element for Sink {
     fn get_enqueue_coord(&self) -> Coord {
         self.coord
     }
-    fn enqueue(&mut self, _: &Packet) {}
+    fn enqueue(&mut self, pkt: &Packet) {
+        check::delivered(pkt);
+    }
     fn get_pkts(&self) -> Vec<Packet> {
         Vec::new()
     }
     fn tick(&mut self) {}
-    fn draw<DB: DrawingBackend>(&self) -> Vec<DynElement<DB, (f64, f64)>> {
+    fn draw(&self, _theme: &Theme) -> Vec<Shape> {
+        Vec::new()
+    }
+}
+
+/// Sinks a flow's acks and records its flow completion time: the number of
+/// ticks from when the flow starts to when the ack for its last packet
+/// arrives. There are no retransmissions, so a flow that loses a packet never
+/// completes. It must be ticked every tick to keep time
+pub struct FctSink {
+    coord: Coord,
+    /// Tick at which the flow starts
+    start: u64,
+    /// Number of packets in the transfer. The flow never completes if this
+    /// is `None`
+    size: Option<u64>,
+    num_acked: u64,
+    tick_num: u64,
+    fct: Option<u64>,
+}
+
+impl FctSink {
+    pub fn new(coord: Coord, start: u64, size: Option<u64>) -> Self {
+        Self {
+            coord,
+            start,
+            size,
+            num_acked: 0,
+            tick_num: 0,
+            fct: None,
+        }
+    }
+
+    /// Flow completion time in ticks, once the flow has completed
+    pub fn fct(&self) -> Option<u64> {
+        self.fct
+    }
+}
+
+impl Element for FctSink {
+    fn get_enqueue_coord(&self) -> Coord {
+        self.coord
+    }
+    fn enqueue(&mut self, pkt: &Packet) {
+        check::delivered(pkt);
+        // Acks thinned out on the way were for packets before this one
+        self.num_acked += u64::max(1, pkt.covers);
+        if self.fct.is_none() && self.size.is_some_and(|size| self.num_acked >= size) {
+            self.fct = Some(self.tick_num - self.start);
+        }
+    }
+    fn get_pkts(&self) -> Vec<Packet> {
         Vec::new()
     }
+    fn tick(&mut self) {
+        self.tick_num += 1;
+    }
+    /// Once the flow completes, shows its completion time next to the sink
+    fn draw(&self, theme: &Theme) -> Vec<Shape> {
+        let Some(fct) = self.fct else {
+            return Vec::new();
+        };
+        vec![theme.text(
+            format!("Done in {} ticks", fct),
+            self.coord.add(Coord(0.3, 0.)),
+            Align::Left,
+        )]
+    }
+}
+
+/// Sinks packets and remembers the tick at which each arrived, for tests and
+/// headless runs. It must be ticked every tick to keep time
+pub struct Recorder {
+    pub coord: Coord,
+    tick_num: u64,
+    received: Vec<(u64, Packet)>,
 }
 
-/// Simply acks packets back
-pub struct Acker<N: Element> {
+impl Recorder {
+    pub fn new(coord: Coord) -> Self {
+        Self {
+            coord,
+            tick_num: 0,
+            received: Vec::new(),
+        }
+    }
+
+    /// Every packet received so far with the tick it arrived at, in order of
+    /// arrival
+    pub fn received(&self) -> &[(u64, Packet)] {
+        &self.received
+    }
+}
+
+impl Element for Recorder {
+    fn get_enqueue_coord(&self) -> Coord {
+        self.coord
+    }
+    fn enqueue(&mut self, pkt: &Packet) {
+        check::delivered(pkt);
+        self.received.push((self.tick_num, *pkt));
+    }
+    fn get_pkts(&self) -> Vec<Packet> {
+        Vec::new()
+    }
+    fn tick(&mut self) {
+        self.tick_num += 1;
+    }
+    fn draw(&self, _theme: &Theme) -> Vec<Shape> {
+        Vec::new()
+    }
+}
+
+/// Simply acks packets back. Acks echo the CE mark of the packet they
+/// acknowledge
+pub struct Acker<N: Element + ?Sized> {
     pub rcv_coord: Coord,
     pub snd_coord: Coord,
     pub next: Rc<RefCell<N>>,
 }
 
-impl<N: Element> Element for Acker<N> {
+impl<N: Element + ?Sized> Element for Acker<N> {
     fn get_enqueue_coord(&self) -> Coord {
         self.rcv_coord
     }
     fn enqueue(&mut self, pkt: &Packet) {
-        let mut pkt = pkt.clone();
+        let mut pkt = *pkt;
         pkt.size = ACK_PKT_WIDTH;
+        pkt.ack = true;
+        pkt.covers = 1;
         pkt.coord = self.snd_coord;
         self.next.borrow_mut().enqueue(&pkt);
     }
@@ -43,7 +158,51 @@ impl<N: Element> Element for Acker<N> {
         Vec::new()
     }
     fn tick(&mut self) {}
-    fn draw<DB: DrawingBackend>(&self) -> Vec<DynElement<DB, (f64, f64)>> {
+    fn draw(&self, _theme: &Theme) -> Vec<Shape> {
         Vec::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ack_path::{AckChannel, AckPath};
+    use crate::scene::{Color, Style};
+    use crate::simulation::Simulation;
+    use crate::traffic::{Pattern, TrafficSource};
+
+    #[test]
+    fn fct_counts_thinned_acks() {
+        let sink = Rc::new(RefCell::new(FctSink::new(Coord(-10., -2.), 0, Some(9))));
+        let ack_path = Rc::new(RefCell::new(AckPath::new(
+            Coord(-8., -2.),
+            AckChannel::Thin { keep_every: 3 },
+            sink.clone(),
+        )));
+        let acker = Rc::new(RefCell::new(Acker {
+            rcv_coord: Coord(10., 0.),
+            snd_coord: Coord(10., -2.),
+            next: ack_path.clone(),
+        }));
+        let template = Packet::new(
+            0.25,
+            Coord(-10., 0.),
+            0,
+            0,
+            Style::from(Color::RED).filled(),
+        );
+        let mut source =
+            TrafficSource::new(Pattern::Cbr { intersend: 2 }, template, acker.clone(), 0);
+        source.set_size(9);
+
+        let mut sim = Simulation::new();
+        sim.add("source", Rc::new(RefCell::new(source)));
+        sim.add("acker", acker);
+        sim.add("ack_path", ack_path);
+        sim.add("sink", sink.clone());
+        sim.run(40);
+        // Only every third ack arrives, but together they cover all 9
+        // packets. The last one is sent on tick 16
+        assert_eq!(sink.borrow().fct(), Some(16));
+    }
+}
@@ -0,0 +1,154 @@
+//! Runs elements without drawing them, for tests and quick experiments
+use crate::base::Element;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// How many packets an element held over a run, sampled at the end of every
+/// tick
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Occupancy {
+    pub max: usize,
+    pub mean: f64,
+    /// At the end of the run
+    pub last: usize,
+}
+
+/// What `Simulation::run` measured
+#[derive(Clone, Debug)]
+pub struct Metrics {
+    pub num_ticks: u64,
+    /// One entry per element, in the order they were added
+    pub occupancy: Vec<(String, Occupancy)>,
+}
+
+impl Metrics {
+    /// Occupancy of the element added as `name`
+    pub fn occupancy(&self, name: &str) -> Option<&Occupancy> {
+        self.occupancy
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, occupancy)| occupancy)
+    }
+}
+
+/// Ticks a set of elements. Unlike the animations, it needs no drawing
+/// backend, so it is cheap enough for unit tests
+pub struct Simulation {
+    /// Ticked in this order every tick
+    elements: Vec<(String, Rc<RefCell<dyn Element>>)>,
+    tick_num: u64,
+}
+
+impl Simulation {
+    pub fn new() -> Self {
+        Self {
+            elements: Vec::new(),
+            tick_num: 0,
+        }
+    }
+
+    /// Elements are ticked in the order they are added, so add them in the
+    /// order packets flow through them, as the animations do
+    pub fn add(&mut self, name: &str, element: Rc<RefCell<dyn Element>>) {
+        self.elements.push((name.to_string(), element));
+    }
+
+    /// Number of ticks run so far
+    pub fn tick_num(&self) -> u64 {
+        self.tick_num
+    }
+
+    /// Tick every element once
+    pub fn tick(&mut self) {
+        for (_, element) in &self.elements {
+            element.borrow_mut().tick();
+        }
+        self.tick_num += 1;
+    }
+
+    /// Run `n_ticks` more ticks and report what happened during them
+    pub fn run(&mut self, n_ticks: u64) -> Metrics {
+        let mut occupancy = vec![Occupancy::default(); self.elements.len()];
+        let mut totals = vec![0; self.elements.len()];
+        for _ in 0..n_ticks {
+            self.tick();
+            for (i, (_, element)) in self.elements.iter().enumerate() {
+                let num_pkts = element.borrow().get_pkts().len();
+                occupancy[i].max = occupancy[i].max.max(num_pkts);
+                occupancy[i].last = num_pkts;
+                totals[i] += num_pkts;
+            }
+        }
+        for (occupancy, total) in occupancy.iter_mut().zip(totals) {
+            occupancy.mean = total as f64 / u64::max(1, n_ticks) as f64;
+        }
+        Metrics {
+            num_ticks: n_ticks,
+            occupancy: self
+                .elements
+                .iter()
+                .map(|(name, _)| name.clone())
+                .zip(occupancy)
+                .collect(),
+        }
+    }
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base::{Coord, Packet, DATA_PKT_WIDTH};
+    use crate::bottleneck::Bottleneck;
+    use crate::scene::{Color, Style};
+    use crate::simple_elems::Recorder;
+    use crate::traffic::{Pattern, TrafficSource};
+
+    #[test]
+    fn overloaded_queue_fills_up() {
+        let recorder = Rc::new(RefCell::new(Recorder::new(Coord(10., 0.))));
+        let bottleneck = Rc::new(RefCell::new(Bottleneck::new(
+            Coord(0., 0.),
+            8,
+            vec![2],
+            vec![recorder.clone()],
+            true,
+        )));
+        let template = Packet::new(
+            DATA_PKT_WIDTH,
+            Coord(-1., 0.),
+            0,
+            0,
+            Style::from(Color::RED).filled(),
+        );
+        let pattern = Pattern::Cbr { intersend: 1 };
+        let mut sim = Simulation::new();
+        sim.add(
+            "source",
+            Rc::new(RefCell::new(TrafficSource::new(
+                pattern,
+                template,
+                bottleneck.clone(),
+                0,
+            ))),
+        );
+        sim.add("bottleneck", bottleneck);
+        sim.add("recorder", recorder.clone());
+
+        let metrics = sim.run(100);
+        assert_eq!(metrics.num_ticks, 100);
+        assert_eq!(sim.tick_num(), 100);
+        let occupancy = metrics.occupancy("bottleneck").unwrap();
+        assert_eq!(occupancy.max, 8);
+        // Full except just after the link sends
+        assert!(occupancy.mean > 7.);
+        // The link sends every other tick
+        assert_eq!(recorder.borrow().received().len(), 50);
+        assert!(metrics.occupancy("missing").is_none());
+    }
+}
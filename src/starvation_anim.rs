@@ -1,8 +1,12 @@
-use plotters::prelude::*;
+use crate::base::Coord;
+use crate::output::{Animation, OutputConfig};
+use crate::pool::Pool;
+use crate::scene::{Color, Shape, Style};
+use crate::sweep::{sweep_anim, KnobLayout, SweepConfig, CANVAS_SIZE};
 use std::error::Error;
 
 fn cca_behavior(link_rate: f64) -> Vec<(f64, f64)> {
-    assert!(link_rate >= 1. / 9. && link_rate <= 1.);
+    assert!((1. / 9. ..=1.).contains(&link_rate));
     let mut res = Vec::new();
     let ss_exit = 1. / link_rate;
     let mut slow_start = true;
@@ -27,125 +31,79 @@ fn cca_behavior(link_rate: f64) -> Vec<(f64, f64)> {
     res
 }
 
-pub fn starvation_anim() -> Result<(), Box<dyn Error>> {
-    let root = BitMapBackend::gif("starvation-knob.gif", (1200, 800), 16)?.into_drawing_area();
-    let (graph, knob) = root.split_vertically(600);
-    const NUM_FRAMES: usize = 33 * 9;
-
-    let grey = ShapeStyle {
-        color: RGBAColor(128, 128, 128, 1.0),
-        filled: false,
-        stroke_width: 1,
+/// The knob animation sweeps the link rate, and two stills show a few link
+/// rates. All three keep their published layout: no axes, and the knob at the
+/// link rate on a bar from 0 to 1. `output` applies to all three. Its ranges
+/// are those of the plot, and default to 0..10 rather than to the curves'
+/// bounds as in other sweeps
+pub fn starvation_anim(output: &OutputConfig, pool: &Pool) -> Result<(), Box<dyn Error>> {
+    let config = SweepConfig {
+        filename: String::from("starvation-knob.gif"),
+        param: String::from("Link rate"),
+        range: 1. / 9.0..1. / 9. + 0.84,
+        // The bar is the link rate's whole range
+        knob_range: Some(0.0..1.0),
+        num_frames: 33 * 9,
+        labels: false,
+        x_desc: String::from("Packet delay"),
+        y_desc: String::from("Time"),
+        output: OutputConfig {
+            x_range: Some(output.x_range.clone().unwrap_or(0.0..10.0)),
+            y_range: Some(output.y_range.clone().unwrap_or(0.0..10.0)),
+            ..output.clone()
+        },
     };
-    let highlight = ShapeStyle {
-        color: RGBAColor(68, 114, 196, 1.0),
-        filled: false,
-        stroke_width: 2,
+    sweep_anim(&config, cca_behavior, pool)?;
+
+    let theme = &output.theme;
+    let muted = Style::from(theme.muted);
+    let highlight = theme.flow_style(0, Style::from(Color(68, 114, 196)).stroke_width(2));
+    let curve = |link_rate, style| Shape::Path {
+        points: cca_behavior(link_rate)
+            .into_iter()
+            .map(|(x, y)| Coord(x, y))
+            .collect(),
+        style,
     };
-
-    for frame in 0..NUM_FRAMES {
-        graph.fill(&WHITE)?;
-        knob.fill(&WHITE)?;
-        let mut chart = ChartBuilder::on(&graph).build_cartesian_2d(0.0..10.0, 0.0..10.0)?;
-        chart
-            .configure_mesh()
-            .disable_x_mesh()
-            .disable_y_mesh()
-            .y_desc("Time")
-            .x_desc("Packet delay")
-            .draw()?;
-
-        // let link_rate = 0.1 + 0.9 * frame as f64 / NUM_FRAMES as f64;
-        let link_rate = 1. / 9. + ((6.28 * frame as f64 / NUM_FRAMES as f64).sin() + 1.) * 0.42;
-
-        chart.draw_series(LineSeries::new(cca_behavior(link_rate), highlight))?;
-
-        let mut knob_chart = ChartBuilder::on(&knob).build_cartesian_2d(-0.1..1.1, -1f32..3f32)?;
-        knob_chart.draw_series(vec![Circle::new(
-            (link_rate, 0.0),
-            15.,
-            Into::<ShapeStyle>::into(&BLACK).filled(),
-        )])?;
-        knob_chart.draw_series(vec![Rectangle::new(
-            [(0.0, -0.05), (1.0, 0.05)],
-            Into::<ShapeStyle>::into(&BLACK).filled(),
-        )])?;
-
-        root.present()?;
-    }
+    let plot_x = output.x_range.clone().unwrap_or(0.0..10.0);
+    let plot_y = output.y_range.clone().unwrap_or(0.0..10.0);
 
     // Plot a graph with multiple lines
-    let root = BitMapBackend::new("starvation-multiple.png", (1200, 800)).into_drawing_area();
-    let (graph, knob) = root.split_vertically(600);
-    graph.fill(&WHITE)?;
-    knob.fill(&WHITE)?;
-
-    let mut chart = ChartBuilder::on(&graph).build_cartesian_2d(0.0..10.0, 0.0..10.0)?;
-    chart
-        .configure_mesh()
-        .disable_x_mesh()
-        .disable_y_mesh()
-        .y_desc("Time")
-        .x_desc("Packet delay")
-        .draw()?;
-
-    for (link_rate, style) in [
-        (0.12, grey),
-        (0.2, grey),
-        (0.3, grey),
-        (0.4, grey),
-        (0.5, grey),
+    let layout = KnobLayout {
+        x_range: plot_x.clone(),
+        y_range: plot_y.clone(),
+    };
+    let mut anim = Animation::new(layout.canvas(output));
+    let mut frame: Vec<_> = [
+        (0.12, muted),
+        (0.2, muted),
+        (0.3, muted),
+        (0.4, muted),
+        (0.5, muted),
         (0.6, highlight),
-        (0.7, grey),
-        (0.8, grey),
-        (0.9, grey),
+        (0.7, muted),
+        (0.8, muted),
+        (0.9, muted),
         (0.95, highlight),
-    ] {
-        chart.draw_series(LineSeries::new(cca_behavior(link_rate), style))?;
-    }
-
-    let mut knob_chart = ChartBuilder::on(&knob).build_cartesian_2d(-0.1..1.1, -1f32..3f32)?;
-    knob_chart.draw_series(vec![Circle::new(
-        (0.95, 0.0),
-        15.,
-        Into::<ShapeStyle>::into(&BLACK).filled(),
-    )])?;
-    let mut knob_chart = ChartBuilder::on(&knob).build_cartesian_2d(-0.1..1.1, -1f32..3f32)?;
-    knob_chart.draw_series(vec![Circle::new(
-        (0.5, 0.0),
-        15.,
-        Into::<ShapeStyle>::into(&BLACK).filled(),
-    )])?;
-    knob_chart.draw_series(vec![Rectangle::new(
-        [(0.0, -0.05), (1.0, 0.05)],
-        Into::<ShapeStyle>::into(&BLACK).filled(),
-    )])?;
+    ]
+    .into_iter()
+    .map(|(link_rate, style)| curve(link_rate, style))
+    .collect();
+    frame.extend(layout.knobs(&[0.95, 0.5], theme));
+    anim.frames.push(frame);
+    output.finish(anim).save_png("starvation-multiple.png")?;
 
     // Plot two graphs with a region around the lines
-    let root = BitMapBackend::new("starvation-area.png", (1200, 800)).into_drawing_area();
-    root.fill(&WHITE)?;
-    let mut chart = ChartBuilder::on(&root).build_cartesian_2d(0.0..10.0, 0.0..10.0)?;
-    chart
-        .configure_mesh()
-        .disable_x_mesh()
-        .disable_y_mesh()
-        .y_desc("Time")
-        .x_desc("Packet delay")
-        .draw()?;
-
+    let mut anim = Animation::new(output.canvas(CANVAS_SIZE, plot_x, plot_y));
     let mut poly = cca_behavior(0.5);
     let mut translated_line = poly.iter().map(|(x, y)| (*x, y - 1.0)).collect::<Vec<_>>();
     translated_line.reverse();
     poly.append(&mut translated_line);
-
-    chart.draw_series(vec![Polygon::new(
-        poly,
-        ShapeStyle {
-            color: RGBAColor(64, 116, 155, 1.0),
-            filled: true,
-            stroke_width: 0,
-        },
-    )])?;
+    anim.frames.push(vec![Shape::Polygon {
+        points: poly.into_iter().map(|(x, y)| Coord(x, y)).collect(),
+        style: theme.flow_style(0, Color(64, 116, 155).filled()),
+    }]);
+    output.finish(anim).save_png("starvation-area.png")?;
 
     Ok(())
 }
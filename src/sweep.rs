@@ -0,0 +1,407 @@
+//! Animates how a scenario's behavior changes as one of its parameters is
+//! swept back and forth, with a knob showing the parameter's current value
+use crate::base::Coord;
+use crate::output::{Animation, Canvas, OutputConfig};
+use crate::pool::Pool;
+use crate::scene::{Align, Color, Shape, Style};
+use crate::theme::Theme;
+use std::error::Error;
+use std::f64::consts::TAU;
+use std::ops::Range;
+
+/// Size of the animation in pixels
+pub const CANVAS_SIZE: (u32, u32) = (1200, 800);
+/// Fraction of the height taken by the plot. The knob gets the rest
+const PLOT_FRACTION: f64 = 0.75;
+/// Coordinates of the knob's own chart. The bar spans 0..1 along y = 0
+const KNOB_X: Range<f64> = -0.1..1.1;
+const KNOB_Y: Range<f64> = -1.0..3.0;
+/// In pixels
+const KNOB_RADIUS: i32 = 15;
+/// Room around the plot for labels, in pixels: left, right, below and above
+const LABEL_ROOM: (f64, f64, f64, f64) = (70., 20., 50., 30.);
+/// In pixels
+const TICK_LEN: i32 = 5;
+
+/// Points to plot for one value of the parameter
+type Curve = Vec<(f64, f64)>;
+
+#[derive(Clone, Default)]
+pub struct SweepConfig {
+    /// Where to output the .gif file
+    pub filename: String,
+    /// Name of the swept parameter, shown above the knob
+    pub param: String,
+    /// Values the parameter takes. It starts in the middle, goes up to the
+    /// end, down to the start and back to the middle
+    pub range: Range<f64>,
+    /// Values at the ends of the knob's bar. `None` for `range`
+    pub knob_range: Option<Range<f64>>,
+    pub num_frames: usize,
+    /// Draw axes with ticks and descriptions, and label the knob with its
+    /// value and the ends of the bar
+    pub labels: bool,
+    /// Axis labels of the plot
+    pub x_desc: String,
+    pub y_desc: String,
+    /// Chart ranges (those of the plot) default to a box around every
+    /// frame's curve
+    pub output: OutputConfig,
+}
+
+impl SweepConfig {
+    /// Value of the parameter in each frame
+    pub fn values(&self) -> Vec<f64> {
+        let len = self.range.end - self.range.start;
+        (0..self.num_frames)
+            .map(|frame| {
+                let phase = TAU * frame as f64 / self.num_frames as f64;
+                self.range.start + len * (phase.sin() + 1.) / 2.
+            })
+            .collect()
+    }
+}
+
+/// A plot with a knob below it, in one set of chart coordinates so both are
+/// drawn as one scene
+pub struct KnobLayout {
+    /// Chart ranges of the plot
+    pub x_range: Range<f64>,
+    pub y_range: Range<f64>,
+}
+
+impl KnobLayout {
+    /// Chart ranges of the whole canvas, which has the knob below the plot
+    pub fn ranges(&self) -> (Range<f64>, Range<f64>) {
+        let y_len = self.y_range.end - self.y_range.start;
+        let knob_len = y_len * (1. - PLOT_FRACTION) / PLOT_FRACTION;
+        (
+            self.x_range.clone(),
+            self.y_range.start - knob_len..self.y_range.end,
+        )
+    }
+
+    /// The canvas to draw on. The ranges in `output` are those of the plot,
+    /// so they are already in `self`
+    pub fn canvas(&self, output: &OutputConfig) -> Canvas {
+        let (x_range, y_range) = self.ranges();
+        Canvas {
+            x_range: x_range.clone(),
+            y_range: y_range.clone(),
+            ..output.canvas(CANVAS_SIZE, x_range, y_range)
+        }
+    }
+
+    /// Chart coordinates of a point in the knob's coordinates
+    fn knob_coord(&self, Coord(x, y): Coord) -> Coord {
+        let (x_range, y_range) = self.ranges();
+        let knob_len = self.y_range.start - y_range.start;
+        Coord(
+            x_range.start
+                + (x - KNOB_X.start) / (KNOB_X.end - KNOB_X.start) * (x_range.end - x_range.start),
+            y_range.start + (y - KNOB_Y.start) / (KNOB_Y.end - KNOB_Y.start) * knob_len,
+        )
+    }
+
+    /// The bar, with a knob at each of `positions` along it
+    pub fn knobs(&self, positions: &[f64], theme: &Theme) -> Vec<Shape> {
+        let style = theme.foreground.filled();
+        let mut shapes = vec![Shape::Rect {
+            corners: (
+                self.knob_coord(Coord(0., -0.05)),
+                self.knob_coord(Coord(1., 0.05)),
+            ),
+            style,
+        }];
+        shapes.extend(positions.iter().map(|pos| Shape::Circle {
+            center: self.knob_coord(Coord(*pos, 0.)),
+            radius: KNOB_RADIUS,
+            style,
+        }));
+        shapes
+    }
+}
+
+/// `scenario` maps a value of the parameter to the curve to plot for it. It
+/// is run once per frame, on the pool, before anything is drawn
+pub fn sweep_anim<F>(config: &SweepConfig, scenario: F, pool: &Pool) -> Result<(), Box<dyn Error>>
+where
+    F: Fn(f64) -> Vec<(f64, f64)> + Send + Sync + 'static,
+{
+    sweep_frames(config, scenario, pool)?.save_gif(&config.filename, pool)
+}
+
+/// Run the scenario for every frame and lay out what to draw
+pub fn sweep_frames<F>(
+    config: &SweepConfig,
+    scenario: F,
+    pool: &Pool,
+) -> Result<Animation, Box<dyn Error>>
+where
+    F: Fn(f64) -> Vec<(f64, f64)> + Send + Sync + 'static,
+{
+    let output = &config.output;
+    let theme = &output.theme;
+    let values = config.values();
+    let curves = run_all(&values, scenario, pool)?;
+    // Fit every frame, so the axes stay put as the knob moves
+    let (x_range, y_range) = bounds(&curves);
+    let x_range = output.x_range.clone().unwrap_or(x_range);
+    let y_range = output.y_range.clone().unwrap_or(y_range);
+
+    let (layout, axes) = if config.labels {
+        // Make room around the plot for the labels
+        let size = output.size.unwrap_or(CANVAS_SIZE);
+        let (left, right, below, above) = LABEL_ROOM;
+        let units_per_px = (
+            (x_range.end - x_range.start) / (size.0 as f64 - left - right),
+            (y_range.end - y_range.start) / (size.1 as f64 * PLOT_FRACTION - below - above),
+        );
+        let layout = KnobLayout {
+            x_range: x_range.start - left * units_per_px.0..x_range.end + right * units_per_px.0,
+            y_range: y_range.start - below * units_per_px.1..y_range.end + above * units_per_px.1,
+        };
+        let axes = axes(config, &x_range, &y_range, units_per_px);
+        (layout, axes)
+    } else {
+        (KnobLayout { x_range, y_range }, Vec::new())
+    };
+
+    let knob_range = config.knob_range.clone().unwrap_or(config.range.clone());
+    let curve_style = theme.flow_style(0, Style::from(Color(68, 114, 196)).stroke_width(2));
+    let mut anim = Animation::new(layout.canvas(output));
+    for (value, curve) in values.iter().zip(curves) {
+        let mut frame = axes.clone();
+        frame.push(Shape::Path {
+            points: curve.into_iter().map(|(x, y)| Coord(x, y)).collect(),
+            style: curve_style,
+        });
+        let pos = (value - knob_range.start) / (knob_range.end - knob_range.start);
+        frame.extend(layout.knobs(&[pos], theme));
+        if config.labels {
+            frame.push(theme.text(
+                format!("{} = {:.2}", config.param, value),
+                layout.knob_coord(Coord(pos, 1.)),
+                Align::Center,
+            ));
+            for (x, end) in [(0., knob_range.start), (1., knob_range.end)] {
+                frame.push(theme.text(
+                    format!("{:.2}", end),
+                    layout.knob_coord(Coord(x, -0.6)),
+                    Align::Center,
+                ));
+            }
+        }
+        anim.frames.push(frame);
+    }
+    Ok(output.finish(anim))
+}
+
+/// Axes along the bottom and left of the plot, with ticks and descriptions
+fn axes(
+    config: &SweepConfig,
+    x_range: &Range<f64>,
+    y_range: &Range<f64>,
+    units_per_px: (f64, f64),
+) -> Vec<Shape> {
+    let theme = &config.output.theme;
+    let style = Style::from(theme.foreground);
+    let origin = Coord(x_range.start, y_range.start);
+    let mut shapes = vec![Shape::Path {
+        points: vec![
+            Coord(x_range.start, y_range.end),
+            origin,
+            Coord(x_range.end, y_range.start),
+        ],
+        style,
+    }];
+    let (x_ticks, x_decimals) = ticks(x_range);
+    for x in x_ticks {
+        shapes.push(Shape::IconPath {
+            at: Coord(x, origin.1),
+            points: vec![(0, 0), (0, TICK_LEN)],
+            style,
+        });
+        shapes.push(theme.text(
+            format!("{:.*}", x_decimals, x),
+            Coord(x, origin.1 - 15. * units_per_px.1),
+            Align::Center,
+        ));
+    }
+    let (y_ticks, y_decimals) = ticks(y_range);
+    for y in y_ticks {
+        shapes.push(Shape::IconPath {
+            at: Coord(origin.0, y),
+            points: vec![(0, 0), (-TICK_LEN, 0)],
+            style,
+        });
+        shapes.push(theme.text(
+            format!("{:.*}", y_decimals, y),
+            Coord(origin.0 - 8. * units_per_px.0, y),
+            Align::Right,
+        ));
+    }
+    shapes.push(theme.text(
+        config.x_desc.clone(),
+        Coord(
+            (x_range.start + x_range.end) / 2.,
+            origin.1 - 35. * units_per_px.1,
+        ),
+        Align::Center,
+    ));
+    shapes.push(theme.text(
+        config.y_desc.clone(),
+        Coord(origin.0, y_range.end + 15. * units_per_px.1),
+        Align::Center,
+    ));
+    shapes
+}
+
+/// Round numbers in `range` to put ticks at, about five of them, and the
+/// number of decimals they need
+fn ticks(range: &Range<f64>) -> (Vec<f64>, usize) {
+    let rough = (range.end - range.start) / 5.;
+    let magnitude = 10f64.powf(rough.log10().floor());
+    let step = [1., 2., 5., 10.]
+        .into_iter()
+        .map(|m| m * magnitude)
+        .find(|step| *step >= rough)
+        .unwrap();
+    let decimals = f64::max(0., -step.log10().floor()) as usize;
+    let first = (range.start / step).ceil() as i64;
+    let last = (range.end / step).floor() as i64;
+    ((first..=last).map(|i| i as f64 * step).collect(), decimals)
+}
+
+/// Run `scenario` for every value on the pool
+fn run_all<F>(values: &[f64], scenario: F, pool: &Pool) -> Result<Vec<Curve>, Box<dyn Error>>
+where
+    F: Fn(f64) -> Vec<(f64, f64)> + Send + Sync + 'static,
+{
+    let mut curves = Vec::with_capacity(values.len());
+    let shared = values.to_vec();
+    pool.ordered(
+        values.len(),
+        move |i| scenario(shared[i]),
+        |curve| {
+            curves.push(curve);
+            Ok(())
+        },
+    )?;
+    Ok(curves)
+}
+
+/// Smallest x and y ranges containing every point
+fn bounds(curves: &[Curve]) -> (Range<f64>, Range<f64>) {
+    let (mut x_min, mut x_max) = (f64::INFINITY, f64::NEG_INFINITY);
+    let (mut y_min, mut y_max) = (f64::INFINITY, f64::NEG_INFINITY);
+    for &(x, y) in curves.iter().flatten() {
+        (x_min, x_max) = (x_min.min(x), x_max.max(x));
+        (y_min, y_max) = (y_min.min(y), y_max.max(y));
+    }
+    if x_min >= x_max || y_min >= y_max {
+        // Nothing, or nothing with any extent, to plot
+        return (0.0..1.0, 0.0..1.0);
+    }
+    (x_min..x_max, y_min..y_max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(num_frames: usize) -> SweepConfig {
+        SweepConfig {
+            param: String::from("x"),
+            range: 2.0..4.0,
+            num_frames,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn values_sweep_the_range() {
+        let values = config(100).values();
+        assert_eq!(values.len(), 100);
+        assert!((values[0] - 3.).abs() < 1e-9);
+        assert!((values[25] - 4.).abs() < 1e-9);
+        assert!((values[75] - 2.).abs() < 1e-9);
+        assert!(values.iter().all(|v| (2.0..=4.0).contains(v)));
+    }
+
+    #[test]
+    fn runs_every_value_in_order() {
+        let values = config(37).values();
+        let curves = run_all(
+            &values,
+            |v| vec![(0., v), (1., v * 2.)],
+            &Pool::with_threads(2),
+        )
+        .unwrap();
+        assert_eq!(curves.len(), values.len());
+        for (curve, v) in curves.iter().zip(&values) {
+            assert_eq!(curve, &vec![(0., *v), (1., v * 2.)]);
+        }
+        let lo = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let hi = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        assert_eq!(bounds(&curves), (0.0..1.0, lo..hi * 2.));
+    }
+
+    #[test]
+    fn knob_follows_the_value() {
+        let pool = Pool::with_threads(1);
+        // The plot spans 0..1, and so does the knob's chart, bar and all
+        let knob_x = |config: &SweepConfig| {
+            let anim = sweep_frames(config, |v| vec![(0., v), (1., v + 1.)], &pool).unwrap();
+            assert_eq!(anim.frames.len(), config.num_frames);
+            match anim.frames[0].last() {
+                Some(Shape::Circle { center, .. }) => center.0 * 1.2 - 0.1,
+                _ => panic!("no knob"),
+            }
+        };
+        // The bar spans the range, and frame 0 is the middle of it
+        assert!((knob_x(&config(4)) - 0.5).abs() < 1e-9);
+        let raw = SweepConfig {
+            knob_range: Some(0.0..10.0),
+            ..config(4)
+        };
+        assert!((knob_x(&raw) - 0.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn labels_are_optional() {
+        let pool = Pool::with_threads(1);
+        let texts = |labels| {
+            let config = SweepConfig {
+                labels,
+                ..config(2)
+            };
+            let anim = sweep_frames(&config, |v| vec![(0., 0.), (10., v)], &pool).unwrap();
+            anim.frames[0]
+                .iter()
+                .filter_map(|shape| match shape {
+                    Shape::Text { text, .. } => Some(text.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        assert!(texts(false).is_empty());
+        let labels = texts(true);
+        assert!(labels.contains(&String::from("x = 3.00")));
+        assert!(labels.contains(&String::from("10")));
+    }
+
+    #[test]
+    fn ticks_are_round() {
+        assert_eq!(
+            ticks(&(0.0..2000.0)),
+            (vec![0., 500., 1000., 1500., 2000.], 0)
+        );
+        let (xs, decimals) = ticks(&(0.1..0.9));
+        assert_eq!(decimals, 1);
+        assert_eq!(
+            xs.iter().map(|x| format!("{:.1}", x)).collect::<Vec<_>>(),
+            ["0.2", "0.4", "0.6", "0.8"]
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! Colors and packet styles shared by every element, so an animation can be
+//! restyled without touching the elements
+use crate::base::Coord;
+use crate::scene::{Align, Color, Shape, Style};
+
+/// How acks are drawn
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AckStyle {
+    /// Like data packets, only narrower
+    Filled,
+    /// Outlined in the flow's color, so they differ from data packets by more
+    /// than size
+    Hollow,
+}
+
+#[derive(Clone, Debug)]
+pub struct Theme {
+    pub background: Color,
+    /// Outlines, buffers and text
+    pub foreground: Color,
+    pub link: Color,
+    /// Curves drawn for comparison behind the one that matters
+    pub muted: Color,
+    /// Fill of hosts and routers
+    pub host: Color,
+    /// Fill of receivers
+    pub receiver: Color,
+    /// If not empty, flow `i` is drawn in `palette[i % len]` instead of the
+    /// color it was configured with
+    pub palette: Vec<Color>,
+    /// Color of packets on their way to being dropped. `None` keeps the
+    /// flow's color
+    pub drop: Option<Color>,
+    /// ECN marking threshold
+    pub mark: Color,
+    /// Width of the border around packets, in pixels. 0 for none
+    pub packet_border: u32,
+    pub ack: AckStyle,
+}
+
+impl Theme {
+    /// Black on white, in the colors each animation was configured with
+    pub fn light() -> Self {
+        Self {
+            background: Color::WHITE,
+            foreground: Color::BLACK,
+            link: Color(160, 160, 160),
+            muted: Color(128, 128, 128),
+            host: Color(220, 220, 220),
+            receiver: Color(200, 220, 240),
+            palette: vec![],
+            drop: None,
+            mark: Color::RED,
+            packet_border: 2,
+            ack: AckStyle::Filled,
+        }
+    }
+
+    /// Light on dark, in the colors each animation was configured with
+    pub fn dark() -> Self {
+        Self {
+            background: Color(30, 30, 30),
+            foreground: Color(230, 230, 230),
+            link: Color(100, 100, 100),
+            muted: Color(120, 120, 120),
+            host: Color(80, 80, 80),
+            receiver: Color(50, 75, 105),
+            palette: vec![],
+            drop: None,
+            mark: Color(255, 90, 90),
+            packet_border: 2,
+            ack: AckStyle::Filled,
+        }
+    }
+
+    /// Flows in the Okabe-Ito palette, which stays distinguishable with the
+    /// common kinds of color blindness. Drops are grey and acks hollow, so
+    /// neither relies on color alone
+    pub fn colorblind() -> Self {
+        Self {
+            palette: vec![
+                Color(230, 159, 0),
+                Color(86, 180, 233),
+                Color(0, 158, 115),
+                Color(204, 121, 167),
+                Color(0, 114, 178),
+                Color(240, 228, 66),
+            ],
+            drop: Some(Color(153, 153, 153)),
+            mark: Color(213, 94, 0),
+            ack: AckStyle::Hollow,
+            ..Self::light()
+        }
+    }
+
+    /// One of the built-in themes: "light", "dark" or "colorblind"
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "light" => Some(Self::light()),
+            "dark" => Some(Self::dark()),
+            "colorblind" => Some(Self::colorblind()),
+            _ => None,
+        }
+    }
+
+    /// Style to draw flow `flow` in, given the style it was configured with
+    pub fn flow_style(&self, flow: u16, style: Style) -> Style {
+        if self.palette.is_empty() {
+            return style;
+        }
+        Style {
+            color: self.palette[flow as usize % self.palette.len()],
+            ..style
+        }
+    }
+
+    /// A line of text in the foreground color
+    pub fn text(&self, text: String, coord: Coord, align: Align) -> Shape {
+        Shape::Text {
+            text,
+            coord,
+            align,
+            color: self.foreground,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::light()
+    }
+}
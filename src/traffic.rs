@@ -0,0 +1,425 @@
+//! Traffic sources that inject packets on their own schedule, e.g. to provide
+//! background load
+use crate::base::{Coord, Element, Packet};
+use crate::check;
+use crate::scene::Shape;
+use crate::sender::Pacer;
+use crate::theme::Theme;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Distribution of the length (in ticks) of on and off periods
+#[derive(Clone, Debug)]
+pub enum Period {
+    Exponential {
+        mean: f64,
+    },
+    /// Heavy tailed. `scale` is the minimum length and `shape` the tail index
+    Pareto {
+        scale: f64,
+        shape: f64,
+    },
+}
+
+impl Period {
+    /// Panics unless periods have a finite, positive mean
+    fn validate(&self) {
+        match self {
+            Period::Exponential { mean } => {
+                assert!(
+                    *mean > 0. && mean.is_finite(),
+                    "mean must be positive, not {}",
+                    mean
+                );
+            }
+            Period::Pareto { scale, shape } => {
+                assert!(
+                    *scale > 0. && scale.is_finite(),
+                    "scale must be positive, not {}",
+                    scale
+                );
+                // The mean is infinite otherwise
+                assert!(
+                    *shape > 1. && shape.is_finite(),
+                    "shape must be more than 1, not {}",
+                    shape
+                );
+            }
+        }
+    }
+
+    fn sample(&self, rng: &mut StdRng) -> f64 {
+        // Inverse transform sampling. `1 - u` is never 0
+        let u: f64 = 1. - rng.gen::<f64>();
+        match self {
+            Period::Exponential { mean } => -mean * u.ln(),
+            Period::Pareto { scale, shape } => scale / u.powf(1. / shape),
+        }
+    }
+}
+
+/// When a source emits packets
+#[derive(Clone, Debug)]
+pub enum Pattern {
+    /// Poisson arrivals with `rate` packets per tick on average
+    Poisson { rate: f64 },
+    /// Alternates between on periods, during which it sends a packet every
+    /// `intersend` ticks, and silent off periods. It starts in an on period
+    OnOff {
+        on: Period,
+        off: Period,
+        intersend: u64,
+    },
+    /// Evenly paced at `rate` packets per tick, which need not divide a tick
+    /// evenly. E.g. 0.4 sends 2 packets every 5 ticks
+    Paced { rate: f64 },
+    /// Constant bit rate: a packet every `intersend` ticks. When it starts
+    /// and stops is up to the source
+    Cbr { intersend: u64 },
+}
+
+/// Sends copies of a template packet to `next` as dictated by its `Pattern`.
+/// By default it sends from the first tick forever, but it can be scheduled
+/// to start late, stop early or send a fixed number of packets
+pub struct TrafficSource<N: Element + ?Sized> {
+    pattern: Pattern,
+    /// Every packet we send is a copy of this. It determines where packets
+    /// start, their address, flow and color
+    template: Packet,
+    next: Rc<RefCell<N>>,
+    rng: StdRng,
+    /// Tick at which we start sending and the tick before which we stop
+    start: u64,
+    stop: u64,
+    /// Number of packets to send in all, if finite
+    size: Option<u64>,
+    num_sent: u64,
+    /// Number of ticks so far
+    tick_num: u64,
+    /// Number of ticks since we started. Patterns are relative to this
+    now: u64,
+    /// When the next packet is due (Poisson)
+    next_arrival: f64,
+    /// Whether we are in an on period and when it ends (OnOff)
+    on: bool,
+    period_start: u64,
+    period_end: u64,
+    /// Spaces out packets (Paced)
+    pacer: Pacer,
+}
+
+impl<N: Element + ?Sized> TrafficSource<N> {
+    /// `seed` makes the random patterns reproducible. Panics if a rate is not
+    /// positive, an intersend time is 0 or a period has no finite, positive
+    /// mean
+    pub fn new(pattern: Pattern, template: Packet, next: Rc<RefCell<N>>, seed: u64) -> Self {
+        match &pattern {
+            Pattern::Poisson { rate } | Pattern::Paced { rate } => {
+                // Also rejects NaN
+                assert!(
+                    *rate > 0. && rate.is_finite(),
+                    "rate must be positive, not {}",
+                    rate
+                );
+            }
+            Pattern::OnOff { on, off, intersend } => {
+                on.validate();
+                off.validate();
+                assert!(*intersend > 0, "intersend must be positive");
+            }
+            Pattern::Cbr { intersend } => {
+                assert!(*intersend > 0, "intersend must be positive");
+            }
+        }
+        let mut rng = StdRng::seed_from_u64(seed);
+        let next_arrival = match &pattern {
+            Pattern::Poisson { rate } => Self::interarrival(*rate, &mut rng),
+            _ => 0.,
+        };
+        let period_end = match &pattern {
+            // Like every later period, the first lasts at least a tick
+            Pattern::OnOff { on, .. } => u64::max(1, on.sample(&mut rng).ceil() as u64),
+            _ => 0,
+        };
+        Self {
+            pattern,
+            template,
+            next,
+            rng,
+            start: 0,
+            stop: u64::MAX,
+            size: None,
+            num_sent: 0,
+            tick_num: 0,
+            now: 0,
+            next_arrival,
+            on: true,
+            period_start: 0,
+            period_end,
+            pacer: Pacer::new(),
+        }
+    }
+
+    /// Don't send anything before `start`. The pattern begins at `start`, so
+    /// e.g. a `Cbr` pattern sends its first packet then
+    pub fn set_start(&mut self, start: u64) {
+        self.start = start;
+    }
+
+    /// Stop sending at `stop`
+    pub fn set_stop(&mut self, stop: u64) {
+        self.stop = stop;
+    }
+
+    /// Stop after sending `size` packets
+    pub fn set_size(&mut self, size: u64) {
+        self.size = Some(size);
+    }
+
+    /// Have we sent everything we will ever send?
+    pub fn done(&self) -> bool {
+        self.tick_num >= self.stop || self.size.is_some_and(|size| self.num_sent >= size)
+    }
+
+    fn interarrival(rate: f64, rng: &mut StdRng) -> f64 {
+        Period::Exponential { mean: 1. / rate }.sample(rng)
+    }
+
+    /// How many packets to send this tick
+    fn num_to_send(&mut self) -> u64 {
+        match &self.pattern {
+            Pattern::Poisson { rate } => {
+                let mut num = 0;
+                while self.next_arrival <= self.now as f64 {
+                    num += 1;
+                    self.next_arrival += Self::interarrival(*rate, &mut self.rng);
+                }
+                num
+            }
+            Pattern::OnOff { on, off, intersend } => {
+                while self.now >= self.period_end {
+                    self.on = !self.on;
+                    let period = if self.on { on } else { off };
+                    self.period_start = self.period_end;
+                    // Every period lasts at least a tick
+                    self.period_end += u64::max(1, period.sample(&mut self.rng).ceil() as u64);
+                }
+                u64::from(self.on && (self.now - self.period_start).is_multiple_of(*intersend))
+            }
+            Pattern::Paced { rate } => {
+                self.pacer.tick(*rate);
+                let mut num = 0;
+                while self.pacer.take() {
+                    num += 1;
+                }
+                num
+            }
+            Pattern::Cbr { intersend } => u64::from(self.now.is_multiple_of(*intersend)),
+        }
+    }
+}
+
+impl<N: Element + ?Sized> Element for TrafficSource<N> {
+    /// Where our packets start
+    fn get_enqueue_coord(&self) -> Coord {
+        self.template.coord
+    }
+
+    /// Panics: sources only send packets
+    fn enqueue(&mut self, _: &Packet) {
+        panic!("TrafficSource does not accept packets");
+    }
+
+    fn get_pkts(&self) -> Vec<Packet> {
+        Vec::new()
+    }
+
+    fn tick(&mut self) {
+        if self.tick_num >= self.start && !self.done() {
+            let mut num = self.num_to_send();
+            if let Some(size) = self.size {
+                num = u64::min(num, size - self.num_sent);
+            }
+            for _ in 0..num {
+                let mut pkt = self.template;
+                check::created(&mut pkt);
+                self.next.borrow_mut().enqueue(&pkt);
+            }
+            self.num_sent += num;
+            self.now += 1;
+        }
+        self.tick_num += 1;
+    }
+
+    fn draw(&self, _theme: &Theme) -> Vec<Shape> {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::{Color, Style};
+    use crate::simple_elems::Recorder;
+
+    fn source_to(pattern: Pattern, recorder: Rc<RefCell<Recorder>>) -> TrafficSource<Recorder> {
+        let template = Packet::new(0.25, Coord(0., 0.), 0, 0, Style::from(Color::RED).filled());
+        TrafficSource::new(pattern, template, recorder, 0)
+    }
+
+    fn source(pattern: Pattern) -> TrafficSource<Recorder> {
+        source_to(
+            pattern,
+            Rc::new(RefCell::new(Recorder::new(Coord(10., 0.)))),
+        )
+    }
+
+    /// Ticks at which `source` sends during its first `num_ticks`
+    fn send_ticks(pattern: Pattern, num_ticks: u64) -> Vec<u64> {
+        let recorder = Rc::new(RefCell::new(Recorder::new(Coord(10., 0.))));
+        let mut source = source_to(pattern, recorder.clone());
+        for _ in 0..num_ticks {
+            source.tick();
+            recorder.borrow_mut().tick();
+        }
+        let received = recorder.borrow().received().to_vec();
+        received.iter().map(|r| r.0).collect()
+    }
+
+    /// Number of packets in each run of packets sent `intersend` apart
+    fn bursts(ticks: &[u64], intersend: u64) -> Vec<u64> {
+        let mut bursts = vec![1];
+        for gap in ticks.windows(2).map(|w| w[1] - w[0]) {
+            assert!(gap >= intersend, "packets {} ticks apart", gap);
+            if gap == intersend {
+                *bursts.last_mut().unwrap() += 1;
+            } else {
+                bursts.push(1);
+            }
+        }
+        bursts
+    }
+
+    fn mean(values: &[u64]) -> f64 {
+        values.iter().sum::<u64>() as f64 / values.len() as f64
+    }
+
+    #[test]
+    fn poisson_has_the_mean_rate() {
+        let ticks = send_ticks(Pattern::Poisson { rate: 0.2 }, 50000);
+        let rate = ticks.len() as f64 / 50000.;
+        assert!((rate - 0.2).abs() < 0.01, "rate {}", rate);
+        // Unlike paced traffic, arrivals bunch up and leave gaps
+        let gaps: Vec<u64> = ticks.windows(2).map(|w| w[1] - w[0]).collect();
+        assert!(gaps.contains(&0));
+        assert!(gaps.iter().any(|gap| *gap > 20));
+    }
+
+    #[test]
+    fn on_off_sends_bursts() {
+        let on_off = Pattern::OnOff {
+            on: Period::Exponential { mean: 20. },
+            off: Period::Exponential { mean: 30. },
+            intersend: 2,
+        };
+        let ticks = send_ticks(on_off, 50000);
+        // On for 2/5 of the time, sending every other tick. Periods are
+        // rounded up to whole ticks, which adds a little
+        let rate = ticks.len() as f64 / 50000.;
+        assert!((rate - 0.2).abs() < 0.02, "rate {}", rate);
+        // A packet every other tick for 20 ticks on average
+        let bursts = bursts(&ticks, 2);
+        assert!(
+            (mean(&bursts) - 10.).abs() < 1.5,
+            "bursts {}",
+            mean(&bursts)
+        );
+    }
+
+    #[test]
+    fn pareto_bursts_are_heavy_tailed() {
+        let on_off = Pattern::OnOff {
+            on: Period::Pareto {
+                scale: 10.,
+                shape: 1.5,
+            },
+            off: Period::Exponential { mean: 30. },
+            intersend: 2,
+        };
+        let ticks = send_ticks(on_off, 50000);
+        // On periods last 30 ticks on average, as long as off periods
+        let rate = ticks.len() as f64 / 50000.;
+        assert!((rate - 0.25).abs() < 0.04, "rate {}", rate);
+        // On periods last at least `scale` ticks, and a few last far longer
+        // than the rest
+        let bursts = bursts(&ticks, 2);
+        assert!(bursts.iter().all(|burst| *burst >= 5));
+        assert!(*bursts.iter().max().unwrap() as f64 > 20. * mean(&bursts));
+    }
+
+    #[test]
+    fn paced_spreads_packets_evenly() {
+        let ticks = send_ticks(Pattern::Paced { rate: 0.25 }, 40);
+        assert_eq!(ticks, (0..10).map(|i| 4 * i + 3).collect::<Vec<_>>());
+        // Several packets a tick, carrying the fraction over
+        let ticks = send_ticks(Pattern::Paced { rate: 2.5 }, 4);
+        assert_eq!(ticks, vec![0, 0, 1, 1, 1, 2, 2, 3, 3, 3]);
+    }
+
+    #[test]
+    fn cbr_runs_from_source_start_to_stop() {
+        let recorder = Rc::new(RefCell::new(Recorder::new(Coord(10., 0.))));
+        let mut source = source_to(Pattern::Cbr { intersend: 5 }, recorder.clone());
+        source.set_start(3);
+        source.set_stop(14);
+        for _ in 0..30 {
+            source.tick();
+            recorder.borrow_mut().tick();
+        }
+        let ticks: Vec<u64> = recorder.borrow().received().iter().map(|r| r.0).collect();
+        assert_eq!(ticks, vec![3, 8, 13]);
+    }
+
+    #[test]
+    #[should_panic(expected = "rate must be positive")]
+    fn rejects_negative_rate() {
+        source(Pattern::Poisson { rate: -0.5 });
+    }
+
+    #[test]
+    #[should_panic(expected = "rate must be positive")]
+    fn rejects_nan_rate() {
+        source(Pattern::Paced { rate: f64::NAN });
+    }
+
+    #[test]
+    #[should_panic(expected = "intersend must be positive")]
+    fn rejects_zero_intersend() {
+        source(Pattern::Cbr { intersend: 0 });
+    }
+
+    #[test]
+    #[should_panic(expected = "mean must be positive")]
+    fn rejects_zero_mean_period() {
+        source(Pattern::OnOff {
+            on: Period::Exponential { mean: 10. },
+            off: Period::Exponential { mean: 0. },
+            intersend: 1,
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "shape must be more than 1")]
+    fn rejects_infinite_mean_period() {
+        source(Pattern::OnOff {
+            on: Period::Pareto {
+                scale: 10.,
+                shape: 1.,
+            },
+            off: Period::Exponential { mean: 10. },
+            intersend: 1,
+        });
+    }
+}
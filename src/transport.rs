@@ -1,43 +1,135 @@
 use crate::base::{Coord, Element, Packet};
-use plotters::prelude::*;
+use crate::scene::Shape;
+use crate::theme::Theme;
 use std::cell::RefCell;
 use std::rc::Rc;
 
+/// Number of straight segments used to approximate a Bezier curve
+const BEZIER_SEGMENTS: usize = 32;
+
+/// Shape of the route a packet takes from where it was enqueued to the next
+/// element
+#[derive(Clone, Debug)]
+pub enum PathShape {
+    Straight,
+    /// Straight segments through each of these intermediate points in order
+    Polyline(Vec<Coord>),
+    /// Cubic Bezier curve with these two control points
+    Bezier(Coord, Coord),
+}
+
+impl PathShape {
+    /// The route from `start` to `end` as a polyline
+    pub fn route(&self, start: Coord, end: Coord) -> Vec<Coord> {
+        match self {
+            PathShape::Straight => vec![start, end],
+            PathShape::Polyline(points) => {
+                let mut res = vec![start];
+                res.extend(points);
+                res.push(end);
+                res
+            }
+            PathShape::Bezier(c1, c2) => (0..=BEZIER_SEGMENTS)
+                .map(|i| {
+                    let t = i as f64 / BEZIER_SEGMENTS as f64;
+                    let u = 1. - t;
+                    start
+                        .mul(u * u * u)
+                        .add(c1.mul(3. * u * u * t))
+                        .add(c2.mul(3. * u * t * t))
+                        .add(end.mul(t * t * t))
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The point at fraction `frac` of the total length along `route`
+fn point_along(route: &[Coord], frac: f64) -> Coord {
+    let total: f64 = route.windows(2).map(|w| w[1].sub(w[0]).norm()).sum();
+    let mut remaining = total * frac;
+    for w in route.windows(2) {
+        let len = w[1].sub(w[0]).norm();
+        if remaining <= len && len > 0. {
+            return w[0].add(w[1].sub(w[0]).mul(remaining / len));
+        }
+        remaining -= len;
+    }
+    *route.last().unwrap()
+}
+
 /// Transports packets from the coordinate at which they were enqueued to the
-/// coordinate returned by `next.get_enqueue_coord`
-pub struct Transport<N: Element> {
+/// coordinate returned by `next.get_enqueue_coord`. Packets move at a constant
+/// speed along the route given by `shape`. A packet keeps the delay that was
+/// in force when it was enqueued, so packets can overtake each other when the
+/// delay drops
+pub struct Transport<N: Element + ?Sized> {
     /// Time taken to traverse the area in ticks
     delay: u64,
+    /// `(tick, delay)` pairs in increasing order of tick, in ticks. Each delay
+    /// replaces `delay` from its tick on
+    delay_schedule: Vec<(u64, u64)>,
     /// All the packets in flight. Stores (pkt, number of ticks since they were
-    /// enqueued, coordinate from which they started, coordinate to which they are headed)
-    pkts: Vec<(Packet, u64, Coord, Coord)>,
+    /// enqueued, delay it will take, route it is following)
+    pkts: Vec<(Packet, u64, u64, Vec<Coord>)>,
+    shape: PathShape,
     next: Rc<RefCell<N>>,
+    tick_num: u64,
 }
 
-impl<N: Element> Transport<N> {
+impl<N: Element + ?Sized> Transport<N> {
     pub fn new(delay: u64, next: Rc<RefCell<N>>) -> Self {
         Self {
             // Quick and dirty hack to slow everything down
             delay: delay * 4,
+            delay_schedule: Vec::new(),
             next,
+            shape: PathShape::Straight,
             pkts: Vec::new(),
+            tick_num: 0,
         }
     }
+
+    /// Change the delay over time. Takes `(tick, delay)` pairs in increasing
+    /// order of tick, with delays in the same units as `new`. Each delay holds
+    /// from its tick until the next one's
+    pub fn set_delay_schedule(&mut self, schedule: Vec<(u64, u64)>) {
+        self.delay_schedule = schedule
+            .into_iter()
+            .map(|(tick, delay)| (tick, delay * 4))
+            .collect();
+    }
+
+    /// Delay that packets enqueued now will take, in ticks
+    pub fn current_delay(&self) -> u64 {
+        self.delay_schedule
+            .iter()
+            .take_while(|(tick, _)| *tick <= self.tick_num)
+            .last()
+            .map_or(self.delay, |(_, delay)| *delay)
+    }
+
+    /// Applies to packets enqueued after this call
+    pub fn set_shape(&mut self, shape: PathShape) {
+        self.shape = shape;
+    }
+
+    /// The route a packet enqueued at `from` will take, as a polyline
+    pub fn link_path(&self, from: Coord) -> Vec<Coord> {
+        self.shape
+            .route(from, self.next.borrow().get_enqueue_coord())
+    }
 }
 
-impl<N: Element> Element for Transport<N> {
+impl<N: Element + ?Sized> Element for Transport<N> {
     fn get_enqueue_coord(&self) -> Coord {
         // Transport is not supposed to supply this info
         unreachable!()
     }
 
     fn enqueue(&mut self, pkt: &Packet) {
-        self.pkts.push((
-            *pkt,
-            0,
-            pkt.coord,
-            self.next.borrow_mut().get_enqueue_coord(),
-        ));
+        let route = self.link_path(pkt.coord);
+        self.pkts.push((*pkt, 0, self.current_delay(), route));
     }
 
     fn get_pkts(&self) -> Vec<Packet> {
@@ -45,29 +137,82 @@ impl<N: Element> Element for Transport<N> {
     }
 
     fn tick(&mut self) {
-        let mut to_remove = Vec::new();
-        for i in 0..self.pkts.len() {
-            let (_, _, start, end) = self.pkts[i];
-            let speed = end.sub(start).div(self.delay as f64);
-
-            self.pkts[i].0.coord = self.pkts[i].0.coord.add(speed);
-            self.pkts[i].1 += 1;
-            if self.pkts[i].1 >= self.delay {
-                self.next.borrow_mut().enqueue(&self.pkts[i].0);
-                to_remove.push(i);
-            }
+        for (pkt, ticks, delay, route) in &mut self.pkts {
+            *ticks += 1;
+            pkt.coord = point_along(route, *ticks as f64 / *delay as f64);
         }
-        for i in to_remove {
-            // Note, `swap_remove` does not preserve order of elements
-            self.pkts.swap_remove(i);
+        let (arrived, in_flight) = self
+            .pkts
+            .drain(..)
+            .partition(|(_, ticks, delay, _)| *ticks >= *delay);
+        self.pkts = in_flight;
+        for (pkt, ..) in arrived {
+            self.next.borrow_mut().enqueue(&pkt);
         }
+        self.tick_num += 1;
     }
 
-    fn draw<DB: DrawingBackend>(&self) -> Vec<DynElement<DB, (f64, f64)>> {
+    fn draw(&self, theme: &Theme) -> Vec<Shape> {
         let mut res = Vec::new();
         for pkt in &self.pkts {
-            res.extend(pkt.0.draw());
+            res.extend(pkt.0.draw(theme));
         }
         res
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::{Color, Style};
+    use crate::simple_elems::Recorder;
+    use crate::simulation::Simulation;
+
+    fn pkt(flow: u16) -> Packet {
+        Packet::new(
+            0.25,
+            Coord(0., 0.),
+            0,
+            flow,
+            Style::from(Color::RED).filled(),
+        )
+    }
+
+    #[test]
+    fn delivers_after_delay() {
+        let recorder = Rc::new(RefCell::new(Recorder::new(Coord(10., 0.))));
+        let transport = Rc::new(RefCell::new(Transport::new(2, recorder.clone())));
+        let mut sim = Simulation::new();
+        sim.add("transport", transport.clone());
+        sim.add("recorder", recorder.clone());
+
+        transport.borrow_mut().enqueue(&pkt(0));
+        let metrics = sim.run(20);
+        // 4 ticks per unit of delay. It arrives during the 8th tick
+        assert_eq!(recorder.borrow().received()[0].0, 7);
+        assert_eq!(metrics.occupancy("transport").unwrap().max, 1);
+        assert_eq!(metrics.occupancy("transport").unwrap().last, 0);
+    }
+
+    #[test]
+    fn delay_schedule_applies_to_later_packets() {
+        let recorder = Rc::new(RefCell::new(Recorder::new(Coord(10., 0.))));
+        let mut transport = Transport::new(4, recorder.clone());
+        transport.set_delay_schedule(vec![(5, 1)]);
+        let transport = Rc::new(RefCell::new(transport));
+        let mut sim = Simulation::new();
+        sim.add("transport", transport.clone());
+        sim.add("recorder", recorder.clone());
+
+        transport.borrow_mut().enqueue(&pkt(0));
+        sim.run(5);
+        assert_eq!(transport.borrow().current_delay(), 4);
+        transport.borrow_mut().enqueue(&pkt(1));
+        sim.run(20);
+
+        // The second packet overtakes the first
+        let received = recorder.borrow().received().to_vec();
+        let arrivals: Vec<_> = received.iter().map(|(t, p)| (*t, p.flow)).collect();
+        assert_eq!(arrivals, vec![(8, 1), (15, 0)]);
+    }
+}
@@ -1,13 +1,21 @@
 //! Animation of the entire path
+use crate::ack_path::{AckChannel, AckPath};
+use crate::annotation::{Annotations, Caption};
 use crate::base::{Coord, Element, Packet, DATA_PKT_WIDTH, PKT_HEIGHT};
 use crate::bottleneck::Bottleneck;
+use crate::check;
+use crate::layout::{Layout, Topology};
+use crate::node::{Diagram, NodeKind};
+use crate::output::{Animation, OutputConfig};
+use crate::pool::Pool;
+use crate::scene::{Color, Style};
 use crate::simple_elems::{Acker, Sink};
 use crate::transport::Transport;
-use plotters::prelude::*;
 use std::cell::RefCell;
 use std::error::Error;
 use std::rc::Rc;
 
+#[derive(Clone, Default)]
 pub struct TwoBottlenecksAnimConfig {
     /// Where to output the .gif file
     pub filename: String,
@@ -24,36 +32,67 @@ pub struct TwoBottlenecksAnimConfig {
     pub num_extra_packets: u64,
     /// Number of ticks to animate
     pub num_ticks: u64,
+    /// Should we draw the current tick?
+    pub show_clock: bool,
+    /// Text to show between given ticks
+    pub captions: Vec<Caption>,
+    /// Size, frame rate and chart ranges of the output
+    pub output: OutputConfig,
     /// Should we draw the buffer for the two bottlenecks?
     pub draw_buffer: (bool, bool),
+    /// What happens to acks after the return bottleneck
+    pub ack_channel: AckChannel,
 }
 
-pub fn two_bottlenecks_anim(config: &TwoBottlenecksAnimConfig) -> Result<(), Box<dyn Error>> {
-    let root = BitMapBackend::gif(&config.filename, (1600, 100), 16)?.into_drawing_area();
+/// Size of the animation in pixels
+pub const CANVAS_SIZE: (u32, u32) = (1600, 100);
+
+pub fn two_bottlenecks_anim(
+    config: &TwoBottlenecksAnimConfig,
+    pool: &Pool,
+) -> Result<(), Box<dyn Error>> {
+    two_bottlenecks_frames(config)?.save_gif(&config.filename, pool)
+}
+
+/// Simulate every tick of the animation
+pub fn two_bottlenecks_frames(
+    config: &TwoBottlenecksAnimConfig,
+) -> Result<Animation, Box<dyn Error>> {
+    let theme = &config.output.theme;
+    let layout = Layout::new(&Topology {
+        hops: vec![config.bufsize1, config.bufsize2],
+        ret_bufsize: config.bufsize1,
+        num_flows: 1,
+        flow_sep: 0.,
+    });
 
     let sink = Rc::new(RefCell::new(Sink {
-        coord: Coord(-9., -PKT_HEIGHT * 2.),
+        coord: layout.sinks[0],
     }));
     let returnpath2 = Rc::new(RefCell::new(Transport::new(16, sink.clone())));
-    let ret_bottleneck = Rc::new(RefCell::new(Bottleneck::new(
-        Coord(
-            DATA_PKT_WIDTH * config.bufsize1 as f64 - 3.33,
-            -PKT_HEIGHT * 2.,
-        ),
+    let mut ret_bottleneck = Bottleneck::new(
+        layout.ret_hop,
         config.bufsize1,
         config.bottleneck1_intersend.clone(),
-        vec![returnpath2.clone()],
+        vec![],
         false,
+    );
+    let ack_path = Rc::new(RefCell::new(AckPath::new(
+        ret_bottleneck.get_dequeue_coord(),
+        config.ack_channel.clone(),
+        returnpath2.clone(),
     )));
+    ret_bottleneck.set_next(vec![ack_path.clone()]);
+    let ret_bottleneck = Rc::new(RefCell::new(ret_bottleneck));
     let returnpath1 = Rc::new(RefCell::new(Transport::new(16, ret_bottleneck.clone())));
     let acker = Rc::new(RefCell::new(Acker {
-        rcv_coord: Coord(9., 0.),
-        snd_coord: Coord(9., -PKT_HEIGHT * 2.),
+        rcv_coord: layout.receivers[0].0,
+        snd_coord: layout.receivers[0].1,
         next: returnpath1.clone(),
     }));
     let departure = Rc::new(RefCell::new(Transport::new(16, acker.clone())));
     let bottleneck2 = Rc::new(RefCell::new(Bottleneck::new(
-        Coord(3.33, 0.),
+        layout.hops[1],
         config.bufsize2,
         config.bottleneck2_intersend.clone(),
         vec![departure.clone()],
@@ -61,7 +100,7 @@ pub fn two_bottlenecks_anim(config: &TwoBottlenecksAnimConfig) -> Result<(), Box
     )));
     let between_2_bottlenecks = Rc::new(RefCell::new(Transport::new(16, bottleneck2.clone())));
     let bottleneck = Rc::new(RefCell::new(Bottleneck::new(
-        Coord(-3.33, 0.),
+        layout.hops[0],
         config.bufsize1,
         config.bottleneck1_intersend.clone(),
         vec![between_2_bottlenecks.clone()],
@@ -74,79 +113,138 @@ pub fn two_bottlenecks_anim(config: &TwoBottlenecksAnimConfig) -> Result<(), Box
         bottleneck2.borrow_mut().draw_buffer(config.draw_buffer.1);
     }
 
+    let mut diagram = Diagram::new();
+    diagram.node(NodeKind::Host, layout.host_coord(0));
+    diagram.node(NodeKind::Router, bottleneck.borrow().get_dequeue_coord());
+    diagram.node(NodeKind::Router, bottleneck2.borrow().get_dequeue_coord());
+    diagram.node(
+        NodeKind::Router,
+        ret_bottleneck.borrow().get_dequeue_coord(),
+    );
+    diagram.node(NodeKind::Receiver, layout.receiver_coord(0));
+    diagram.link(&*arrival.borrow(), layout.senders[0]);
+    diagram.link(
+        &*between_2_bottlenecks.borrow(),
+        bottleneck.borrow().get_dequeue_coord(),
+    );
+    diagram.link(
+        &*departure.borrow(),
+        bottleneck2.borrow().get_dequeue_coord(),
+    );
+    diagram.link(&*returnpath1.borrow(), layout.receivers[0].1);
+    diagram.link(
+        &*returnpath2.borrow(),
+        ret_bottleneck.borrow().get_dequeue_coord(),
+    );
+
+    let mut annotations = Annotations::new();
+    annotations.label(
+        "Bottleneck 1",
+        bottleneck.borrow().get_enqueue_coord(),
+        Coord(
+            DATA_PKT_WIDTH * (config.bufsize1 as f64 / 2. + 1.),
+            PKT_HEIGHT * 1.1,
+        ),
+    );
+    annotations.label(
+        "Bottleneck 2",
+        bottleneck2.borrow().get_enqueue_coord(),
+        Coord(
+            DATA_PKT_WIDTH * (config.bufsize2 as f64 / 2. + 1.),
+            PKT_HEIGHT * 1.1,
+        ),
+    );
+    annotations.label(
+        "Receiver",
+        acker.borrow().get_enqueue_coord(),
+        Coord(0., PKT_HEIGHT * 1.1),
+    );
+    if config.show_clock {
+        annotations.clock(layout.clock_coord());
+    }
+    annotations.captions(layout.caption_coord(), config.captions.clone());
+
     let mut num_packets = 0;
+    let mut anim = Animation::new(config.output.canvas(
+        CANVAS_SIZE,
+        layout.x_range.clone(),
+        layout.y_range.clone(),
+    ));
     for tick in 0..config.num_ticks {
-        root.fill(&WHITE)?;
-        let chart = ChartBuilder::on(&root).build_cartesian_2d(-10.0..10.0, -5.0..5.0)?;
+        let mut frame = diagram.draw(theme);
 
         let mut arrival = arrival.borrow_mut();
 
         if tick % config.sender_intersend == 0
             || (tick % (config.sender_intersend / 2) == 0 && num_packets < config.num_extra_packets)
         {
-            arrival.enqueue(&Packet {
-                size: DATA_PKT_WIDTH,
-                coord: Coord(-10., 0.),
-                addr: 0,
-                style: ShapeStyle::from(RED).filled(),
-            });
+            let mut pkt = Packet::new(
+                DATA_PKT_WIDTH,
+                layout.senders[0],
+                0,
+                0,
+                Style::from(Color::RED).filled(),
+            );
+            check::created(&mut pkt);
+            arrival.enqueue(&pkt);
             num_packets += 1;
         }
 
         arrival.tick();
-        for e in arrival.draw() {
-            chart.plotting_area().draw(&e)?;
-        }
+        frame.extend(arrival.draw(theme));
 
         let mut bottleneck = bottleneck.borrow_mut();
         bottleneck.tick();
-        for e in bottleneck.draw() {
-            chart.plotting_area().draw(&e)?;
-        }
+        frame.extend(bottleneck.draw(theme));
 
         let mut between_2_bottlenecks = between_2_bottlenecks.borrow_mut();
         between_2_bottlenecks.tick();
-        for e in between_2_bottlenecks.draw() {
-            chart.plotting_area().draw(&e)?;
-        }
+        frame.extend(between_2_bottlenecks.draw(theme));
 
         let mut bottleneck2 = bottleneck2.borrow_mut();
         bottleneck2.tick();
-        for e in bottleneck2.draw() {
-            chart.plotting_area().draw(&e)?;
-        }
+        frame.extend(bottleneck2.draw(theme));
 
         let mut departure = departure.borrow_mut();
         departure.tick();
-        for e in departure.draw() {
-            chart.plotting_area().draw(&e)?;
-        }
+        frame.extend(departure.draw(theme));
 
         let mut acker = acker.borrow_mut();
         acker.tick();
-        for e in acker.draw() {
-            chart.plotting_area().draw(&e)?;
-        }
+        frame.extend(acker.draw(theme));
 
         let mut returnpath1 = returnpath1.borrow_mut();
         returnpath1.tick();
-        for e in returnpath1.draw() {
-            chart.plotting_area().draw(&e)?;
-        }
+        frame.extend(returnpath1.draw(theme));
 
         let mut ret_bottleneck = ret_bottleneck.borrow_mut();
         ret_bottleneck.tick();
-        for e in ret_bottleneck.draw() {
-            chart.plotting_area().draw(&e)?;
-        }
+        frame.extend(ret_bottleneck.draw(theme));
+
+        let mut ack_path = ack_path.borrow_mut();
+        ack_path.tick();
+        frame.extend(ack_path.draw(theme));
 
         let mut returnpath2 = returnpath2.borrow_mut();
         returnpath2.tick();
-        for e in returnpath2.draw() {
-            chart.plotting_area().draw(&e)?;
-        }
+        frame.extend(returnpath2.draw(theme));
+
+        frame.extend(annotations.draw(tick, theme));
+
+        anim.frames.push(frame);
+    }
 
-        root.present()?;
+    if check::is_enabled() {
+        let mut in_flight = arrival.borrow().get_pkts();
+        in_flight.extend(bottleneck.borrow().get_pkts());
+        in_flight.extend(between_2_bottlenecks.borrow().get_pkts());
+        in_flight.extend(bottleneck2.borrow().get_pkts());
+        in_flight.extend(departure.borrow().get_pkts());
+        in_flight.extend(returnpath1.borrow().get_pkts());
+        in_flight.extend(ret_bottleneck.borrow().get_pkts());
+        in_flight.extend(ack_path.borrow().get_pkts());
+        in_flight.extend(returnpath2.borrow().get_pkts());
+        check::finish(in_flight)?;
     }
-    Ok(())
+    Ok(config.output.finish(anim))
 }
@@ -0,0 +1,389 @@
+//! A shared wireless medium where stations contend for the channel and send
+//! aggregated frames
+use crate::base::{Coord, Element, Packet, DATA_PKT_WIDTH, PKT_HEIGHT};
+use crate::check;
+use crate::scene::{Shape, Style};
+use crate::simple_elems::Sink;
+use crate::theme::Theme;
+use crate::transport::Transport;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// Vertical distance between the queues of adjacent stations
+const STATION_SEP: f64 = PKT_HEIGHT * 1.5;
+
+/// Timing of the MAC, in ticks
+#[derive(Clone, Debug)]
+pub struct Mac {
+    /// Most packets that can be aggregated into one frame
+    pub max_batch: usize,
+    /// Fixed cost of every transmission (preamble, block ack etc.)
+    pub overhead: u64,
+    /// Airtime of each packet in a frame
+    pub per_pkt: u64,
+    /// Stations back off for a random number of ticks below this before
+    /// transmitting
+    pub max_backoff: u64,
+}
+
+impl Default for Mac {
+    /// Aggregation worth a few times the fixed cost, as in 802.11n and later
+    fn default() -> Self {
+        Self {
+            max_batch: 6,
+            overhead: 10,
+            per_pkt: 2,
+            max_backoff: 16,
+        }
+    }
+}
+
+enum State {
+    Idle,
+    /// The channel goes to `station` once `remaining` ticks are up
+    Backoff {
+        station: usize,
+        remaining: u64,
+    },
+    /// `station` is sending the first `num_pkts` packets in its queue, which
+    /// will be done in `remaining` ticks
+    Transmitting {
+        station: usize,
+        num_pkts: usize,
+        remaining: u64,
+    },
+}
+
+/// Every station has its own queue, picked by the packet's address. When the
+/// channel is free, stations with packets contend for it by drawing a random
+/// backoff; the lowest wins. The winner sends up to `max_batch` packets as one
+/// frame, and they all leave together once the frame's airtime is up.
+/// Collisions are not modeled
+pub struct SharedMedium<N: Element + ?Sized> {
+    /// Left center of the station queues, which are stacked vertically around
+    /// it
+    coord: Coord,
+    bufsize: u64,
+    mac: Mac,
+    queues: Vec<VecDeque<Packet>>,
+    state: State,
+    rng: StdRng,
+    /// Used to drop packets
+    dropper: Transport<Sink>,
+    /// Station `i` sends to `next[i]`
+    next: Vec<Rc<RefCell<N>>>,
+}
+
+impl<N: Element + ?Sized> SharedMedium<N> {
+    /// There is one station per entry in `next`. `next` can be empty when
+    /// constructing and filled with `set_next` afterward. `seed` makes the
+    /// backoffs reproducible. Panics if a frame can't hold a packet
+    pub fn new(coord: Coord, bufsize: u64, mac: Mac, next: Vec<Rc<RefCell<N>>>, seed: u64) -> Self {
+        assert!(mac.max_batch > 0, "max_batch must be positive");
+        let dropper = Transport::new(
+            16,
+            Rc::new(RefCell::new(Sink {
+                coord: coord.sub(Coord(DATA_PKT_WIDTH, 5.)),
+            })),
+        );
+        Self {
+            coord,
+            bufsize,
+            mac,
+            queues: next.iter().map(|_| VecDeque::new()).collect(),
+            state: State::Idle,
+            rng: StdRng::seed_from_u64(seed),
+            dropper,
+            next,
+        }
+    }
+
+    /// Replaces the stations. Call this before enqueuing any packets
+    pub fn set_next(&mut self, next: Vec<Rc<RefCell<N>>>) {
+        self.queues = next.iter().map(|_| VecDeque::new()).collect();
+        self.next = next;
+    }
+
+    /// Where packets leave the queues
+    pub fn get_dequeue_coord(&self) -> Coord {
+        self.coord
+            .add(Coord(self.bufsize as f64 * DATA_PKT_WIDTH, 0.))
+    }
+
+    /// Left center of station `i`'s queue
+    fn station_coord(&self, i: usize) -> Coord {
+        let offset = (self.queues.len() as f64 - 1.) / 2. - i as f64;
+        self.coord.add(Coord(0., offset * STATION_SEP))
+    }
+
+    /// Position the packets in station `i`'s queue, front first
+    fn place(&mut self, i: usize) {
+        let exit = self
+            .station_coord(i)
+            .add(Coord(self.bufsize as f64 * DATA_PKT_WIDTH, 0.));
+        let mut x = exit.0;
+        for pkt in &mut self.queues[i] {
+            x -= pkt.size;
+            pkt.coord = Coord(x, exit.1);
+        }
+    }
+
+    /// Tell `check` how full every station's queue is
+    fn check_occupancy(&self) {
+        if !check::is_enabled() {
+            return;
+        }
+        for queue in &self.queues {
+            check::queue(queue.len() as f64, self.bufsize as f64);
+        }
+    }
+
+    /// Pick the station that gets the channel next, if anyone wants it
+    fn contend(&mut self) {
+        let mut best = None;
+        for (i, queue) in self.queues.iter().enumerate() {
+            if queue.is_empty() {
+                continue;
+            }
+            let backoff = self.rng.gen_range(0..self.mac.max_backoff.max(1));
+            if best.is_none_or(|(_, b)| backoff < b) {
+                best = Some((i, backoff));
+            }
+        }
+        if let Some((station, remaining)) = best {
+            self.state = State::Backoff { station, remaining };
+        }
+    }
+}
+
+impl<N: Element + ?Sized> Element for SharedMedium<N> {
+    fn get_enqueue_coord(&self) -> Coord {
+        self.coord.sub(Coord(DATA_PKT_WIDTH, 0.))
+    }
+
+    fn enqueue(&mut self, pkt: &Packet) {
+        let i = pkt.addr as usize;
+        match self.queues.get_mut(i) {
+            Some(queue) if (queue.len() as u64) < self.bufsize => {
+                queue.push_back(*pkt);
+                self.place(i);
+            }
+            // Full, or there is no such station
+            _ => {
+                check::dropped(pkt);
+                self.dropper.enqueue(pkt);
+            }
+        }
+        self.check_occupancy();
+    }
+
+    fn get_pkts(&self) -> Vec<Packet> {
+        self.queues.iter().flatten().copied().collect()
+    }
+
+    fn tick(&mut self) {
+        if let State::Idle = self.state {
+            self.contend();
+        }
+        match self.state {
+            State::Idle => {}
+            State::Backoff { station, remaining } => {
+                if remaining == 0 {
+                    let num_pkts = usize::min(self.mac.max_batch, self.queues[station].len());
+                    self.state = State::Transmitting {
+                        station,
+                        num_pkts,
+                        remaining: self.mac.overhead + self.mac.per_pkt * num_pkts as u64,
+                    };
+                } else {
+                    self.state = State::Backoff {
+                        station,
+                        remaining: remaining - 1,
+                    };
+                }
+            }
+            State::Transmitting {
+                station,
+                num_pkts,
+                remaining,
+            } => {
+                if remaining <= 1 {
+                    // The whole frame leaves at once
+                    for pkt in self.queues[station].drain(..num_pkts) {
+                        self.next[station].borrow_mut().enqueue(&pkt);
+                    }
+                    self.place(station);
+                    self.state = State::Idle;
+                } else {
+                    self.state = State::Transmitting {
+                        station,
+                        num_pkts,
+                        remaining: remaining - 1,
+                    };
+                }
+            }
+        }
+        self.dropper.tick();
+        self.check_occupancy();
+    }
+
+    fn draw(&self, theme: &Theme) -> Vec<Shape> {
+        let width = self.bufsize as f64 * DATA_PKT_WIDTH;
+        let mut res = Vec::new();
+        for i in 0..self.queues.len() {
+            let Coord(x, y) = self.station_coord(i);
+            // Outline the station that has the channel
+            let style = match self.state {
+                State::Transmitting { station, .. } if station == i => {
+                    Style::from(theme.foreground).stroke_width(3)
+                }
+                _ => Style::from(theme.foreground),
+            };
+            res.push(Shape::Path {
+                points: vec![
+                    Coord(x, y - PKT_HEIGHT * 0.55),
+                    Coord(x + width, y - PKT_HEIGHT * 0.55),
+                    Coord(x + width, y + PKT_HEIGHT * 0.55),
+                    Coord(x, y + PKT_HEIGHT * 0.55),
+                ],
+                style,
+            });
+        }
+        for pkt in self.queues.iter().flatten() {
+            res.extend(pkt.draw(theme));
+        }
+        for pkt in self.dropper.get_pkts() {
+            res.extend(pkt.draw_dropped(theme));
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::Color;
+    use crate::simple_elems::Recorder;
+    use crate::simulation::Simulation;
+
+    fn pkt(addr: u16) -> Packet {
+        Packet::new(
+            DATA_PKT_WIDTH,
+            Coord(-10., 0.),
+            addr,
+            addr,
+            Style::from(Color::RED).filled(),
+        )
+    }
+
+    /// A medium whose stations send straight to recorders
+    struct Setup {
+        sim: Simulation,
+        medium: Rc<RefCell<SharedMedium<Recorder>>>,
+        recorders: Vec<Rc<RefCell<Recorder>>>,
+    }
+
+    fn setup(num_stations: usize, bufsize: u64, mac: Mac, seed: u64) -> Setup {
+        let recorders: Vec<_> = (0..num_stations)
+            .map(|_| Rc::new(RefCell::new(Recorder::new(Coord(10., 0.)))))
+            .collect();
+        let medium = Rc::new(RefCell::new(SharedMedium::new(
+            Coord(0., 0.),
+            bufsize,
+            mac,
+            recorders.clone(),
+            seed,
+        )));
+        let mut sim = Simulation::new();
+        sim.add("medium", medium.clone());
+        for (i, recorder) in recorders.iter().enumerate() {
+            sim.add(&format!("recorder {}", i), recorder.clone());
+        }
+        Setup {
+            sim,
+            medium,
+            recorders,
+        }
+    }
+
+    #[test]
+    fn only_stations_with_packets_contend() {
+        for seed in 0..20 {
+            let Setup { medium, .. } = setup(3, 8, Mac::default(), seed);
+            medium.borrow_mut().tick();
+            assert!(matches!(medium.borrow().state, State::Idle));
+            medium.borrow_mut().enqueue(&pkt(2));
+            medium.borrow_mut().tick();
+            assert!(matches!(
+                medium.borrow().state,
+                State::Backoff { station: 2, .. } | State::Transmitting { station: 2, .. }
+            ));
+        }
+    }
+
+    #[test]
+    fn frames_leave_together_after_their_airtime() {
+        let mac = Mac {
+            max_batch: 3,
+            overhead: 10,
+            per_pkt: 2,
+            // No backoff
+            max_backoff: 1,
+        };
+        let Setup {
+            mut sim,
+            medium,
+            recorders,
+        } = setup(2, 8, mac, 0);
+        for _ in 0..5 {
+            medium.borrow_mut().enqueue(&pkt(1));
+        }
+        sim.run(40);
+        // A frame of 3 takes 10 + 2 * 3 ticks and the remaining 2 take
+        // 10 + 2 * 2 after that
+        let ticks: Vec<_> = recorders[1]
+            .borrow()
+            .received()
+            .iter()
+            .map(|r| r.0)
+            .collect();
+        assert_eq!(ticks, vec![16, 16, 16, 31, 31]);
+        assert!(recorders[0].borrow().received().is_empty());
+    }
+
+    #[test]
+    fn drops_per_station() {
+        check::enable();
+        let Setup { medium, .. } = setup(2, 2, Mac::default(), 0);
+        // Station 0 overflows, station 1 has room and there is no station 5
+        for addr in [0, 0, 0, 1, 5] {
+            let mut pkt = pkt(addr);
+            check::created(&mut pkt);
+            medium.borrow_mut().enqueue(&pkt);
+        }
+        let medium = medium.borrow();
+        let lens: Vec<_> = medium.queues.iter().map(|q| q.len()).collect();
+        assert_eq!(lens, vec![2, 1]);
+        let dropped: Vec<_> = medium.dropper.get_pkts().iter().map(|p| p.addr).collect();
+        assert_eq!(dropped, vec![0, 5]);
+        let report = check::finish(medium.get_pkts()).unwrap();
+        assert_eq!(report.num_dropped, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "max_batch must be positive")]
+    fn rejects_empty_frames() {
+        setup(
+            2,
+            8,
+            Mac {
+                max_batch: 0,
+                ..Default::default()
+            },
+            0,
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! Animate flows from several stations sharing a Wi-Fi style medium
+use crate::annotation::{Annotations, Caption};
+use crate::base::{Coord, Element, Packet, DATA_PKT_WIDTH, PKT_HEIGHT};
+use crate::check;
+use crate::dumbbell_anim::FlowConfig;
+use crate::layout::{Layout, Topology};
+use crate::node::{Diagram, NodeKind};
+use crate::output::{Animation, OutputConfig};
+use crate::pool::Pool;
+use crate::scene::Style;
+use crate::simple_elems::Acker;
+use crate::transport::Transport;
+use crate::wifi::{Mac, SharedMedium};
+use std::cell::RefCell;
+use std::error::Error;
+use std::rc::Rc;
+
+#[derive(Clone, Default)]
+pub struct WifiAnimConfig {
+    /// Where to output the .gif file
+    pub filename: String,
+    /// Buffer size of each station
+    pub bufsize: u64,
+    pub mac: Mac,
+    /// One station per flow
+    pub flows: Vec<FlowConfig>,
+    /// Number of ticks to animate
+    pub num_ticks: u64,
+    /// Should we draw the current tick?
+    pub show_clock: bool,
+    /// Text to show between given ticks
+    pub captions: Vec<Caption>,
+    /// Size, frame rate and chart ranges of the output
+    pub output: OutputConfig,
+}
+
+pub fn wifi_anim(config: &WifiAnimConfig, pool: &Pool) -> Result<(), Box<dyn Error>> {
+    wifi_frames(config)?.save_gif(&config.filename, pool)
+}
+
+/// Simulate every tick of the animation
+pub fn wifi_frames(config: &WifiAnimConfig) -> Result<Animation, Box<dyn Error>> {
+    let theme = &config.output.theme;
+    let layout = Layout::new(&Topology {
+        hops: vec![config.bufsize],
+        ret_bufsize: 0,
+        num_flows: config.flows.len(),
+        flow_sep: PKT_HEIGHT * 6.,
+    });
+    let mut anim = Animation::new(config.output.canvas(
+        (1600, layout.canvas_height()),
+        layout.x_range.clone(),
+        layout.y_range.clone(),
+    ));
+
+    // We will populate `next` after constructing the flows
+    let medium = Rc::new(RefCell::new(SharedMedium::<dyn Element>::new(
+        layout.hops[0],
+        config.bufsize,
+        config.mac.clone(),
+        vec![],
+        0,
+    )));
+
+    // (arrival, departure, acker, returnpath, sink) for each flow. Acks go
+    // straight back to the sender
+    let mut flows = Vec::new();
+    for (i, flow) in config.flows.iter().enumerate() {
+        let (arrival, departure) = (flow.rtt * 2 / 5, flow.rtt / 5);
+        let returnpath = flow.rtt - arrival - departure;
+
+        let sink = Rc::new(RefCell::new(flow.sink(layout.sinks[i])));
+        let returnpath = Rc::new(RefCell::new(Transport::new(returnpath, sink.clone())));
+        let acker = Rc::new(RefCell::new(Acker {
+            rcv_coord: layout.receivers[i].0,
+            snd_coord: layout.receivers[i].1,
+            next: returnpath.clone(),
+        }));
+        let departure = Rc::new(RefCell::new(Transport::new(departure, acker.clone())));
+        let arrival = Rc::new(RefCell::new(Transport::new(arrival, medium.clone())));
+        flows.push((arrival, departure, acker, returnpath, sink));
+    }
+
+    medium.borrow_mut().set_next(
+        flows
+            .iter()
+            .map(|f| f.1.clone() as Rc<RefCell<dyn Element>>)
+            .collect(),
+    );
+
+    let mut sources: Vec<_> = config
+        .flows
+        .iter()
+        .zip(&flows)
+        .enumerate()
+        .map(|(i, (flow, (arrival, ..)))| {
+            let template = Packet::new(
+                DATA_PKT_WIDTH,
+                layout.senders[i],
+                i as u16,
+                i as u16,
+                Style::from(flow.color).filled(),
+            );
+            flow.source(template, arrival.clone(), i as u64 + 1)
+        })
+        .collect();
+
+    let mut diagram = Diagram::new();
+    for (i, (arrival, departure, _, returnpath, _)) in flows.iter().enumerate() {
+        diagram.node(NodeKind::Host, layout.host_coord(i));
+        diagram.node(NodeKind::Receiver, layout.receiver_coord(i));
+        diagram.link(&*arrival.borrow(), layout.senders[i]);
+        diagram.link(&*departure.borrow(), medium.borrow().get_dequeue_coord());
+        diagram.link(&*returnpath.borrow(), layout.receivers[i].1);
+    }
+
+    let mut annotations = Annotations::new();
+    annotations.label(
+        "Shared medium",
+        medium.borrow().get_enqueue_coord(),
+        Coord(
+            DATA_PKT_WIDTH * (config.bufsize as f64 / 2. + 1.),
+            PKT_HEIGHT * (config.flows.len() as f64 * 0.75 + 0.6),
+        ),
+    );
+    annotations.legend(
+        layout.legend_coord(),
+        config
+            .flows
+            .iter()
+            .enumerate()
+            .map(|(i, f)| (format!("Station {}", i + 1), Style::from(f.color).filled()))
+            .collect(),
+    );
+    if config.show_clock {
+        annotations.clock(layout.clock_coord());
+    }
+    annotations.captions(layout.caption_coord(), config.captions.clone());
+
+    for tick in 0..config.num_ticks {
+        let mut frame = diagram.draw(theme);
+
+        // Produce packets
+        for (source, (arrival, ..)) in sources.iter_mut().zip(&flows) {
+            source.tick();
+            let mut arrival = arrival.borrow_mut();
+            arrival.tick();
+            frame.extend(arrival.draw(theme));
+        }
+
+        {
+            let mut medium = medium.borrow_mut();
+            medium.tick();
+            frame.extend(medium.draw(theme));
+        }
+
+        for (_, departure, acker, returnpath, sink) in &flows {
+            let mut departure = departure.borrow_mut();
+            departure.tick();
+            frame.extend(departure.draw(theme));
+
+            let mut acker = acker.borrow_mut();
+            acker.tick();
+            frame.extend(acker.draw(theme));
+
+            let mut returnpath = returnpath.borrow_mut();
+            returnpath.tick();
+            frame.extend(returnpath.draw(theme));
+
+            let mut sink = sink.borrow_mut();
+            sink.tick();
+            frame.extend(sink.draw(theme));
+        }
+
+        frame.extend(annotations.draw(tick, theme));
+
+        anim.frames.push(frame);
+    }
+
+    if check::is_enabled() {
+        let mut in_flight = medium.borrow().get_pkts();
+        for (arrival, departure, _, returnpath, _) in &flows {
+            in_flight.extend(arrival.borrow().get_pkts());
+            in_flight.extend(departure.borrow().get_pkts());
+            in_flight.extend(returnpath.borrow().get_pkts());
+        }
+        check::finish(in_flight)?;
+    }
+    Ok(config.output.finish(anim))
+}
@@ -0,0 +1,166 @@
+//! Renders chosen ticks of the presets and compares them with the images in
+//! `tests/golden`, so refactors don't silently change published animations.
+//! Text is left out, since how it renders depends on the fonts installed.
+//! After an intentional change, regenerate the images with
+//!
+//!     UPDATE_GOLDENS=1 cargo test --test golden
+use anim::output::Animation;
+use anim::presets;
+use anim::scene::Shape;
+use anim::theme::Theme;
+use anim::{
+    dumbbell_anim, elem_anim, fair_anim, parking_lot_anim, path_anim, two_bottlenecks_anim,
+};
+use image::RgbImage;
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Ticks of every preset to compare
+const TICKS: [u64; 2] = [120, 400];
+/// Ticks to simulate so every tick in `TICKS` is drawn
+const NUM_TICKS: u64 = 401;
+/// A pixel counts as changed if any channel differs by more than this
+const CHANNEL_TOLERANCE: u8 = 16;
+/// Fail if more than this fraction of pixels changed
+const MAX_CHANGED: f64 = 0.002;
+
+/// Rasterize `tick` of `anim`, without text, and compare it with its golden
+/// image
+fn compare_tick(filename: &str, anim: &Animation, tick: u64) {
+    let mut frame = anim.frames[tick as usize].clone();
+    frame.retain(|shape| !matches!(shape, Shape::Text { .. }));
+    let textless = Animation {
+        canvas: anim.canvas.clone(),
+        frames: vec![frame],
+    };
+    let (w, h) = textless.canvas.pixel_size();
+    let buf = textless.rasterize(0).unwrap();
+    let actual = RgbImage::from_raw(w, h, buf).unwrap();
+    compare(&golden_name(filename, tick), &actual);
+}
+
+/// Name of the golden image for `tick` of the preset that writes `filename`
+fn golden_name(filename: &str, tick: u64) -> String {
+    let stem = Path::new(filename).file_stem().unwrap().to_str().unwrap();
+    format!("{}-{}.png", stem, tick)
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(name)
+}
+
+/// Compare `actual` with the golden image `name`, or overwrite the golden if
+/// UPDATE_GOLDENS is set
+fn compare(name: &str, actual: &RgbImage) {
+    let path = golden_path(name);
+    if env::var_os("UPDATE_GOLDENS").is_some() {
+        actual.save(&path).unwrap();
+        return;
+    }
+    let golden = image::open(&path)
+        .unwrap_or_else(|e| panic!("can't open {}: {}. Set UPDATE_GOLDENS=1", path.display(), e))
+        .to_rgb8();
+    assert_eq!(golden.dimensions(), actual.dimensions(), "{}", name);
+
+    let num_changed = golden
+        .pixels()
+        .zip(actual.pixels())
+        .filter(|(g, a)| {
+            g.0.iter()
+                .zip(a.0)
+                .any(|(g, a)| g.abs_diff(a) > CHANNEL_TOLERANCE)
+        })
+        .count();
+    let frac = num_changed as f64 / golden.pixels().len() as f64;
+    if frac > MAX_CHANGED {
+        let failed = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+        actual.save(&failed).unwrap();
+        panic!(
+            "{}: {:.2}% of pixels changed. Wrote the new frame to {}",
+            name,
+            frac * 100.,
+            failed.display()
+        );
+    }
+}
+
+#[test]
+fn path() {
+    for mut config in presets::path() {
+        config.num_ticks = NUM_TICKS;
+        let anim = path_anim::path_frames(&config).unwrap();
+        for tick in TICKS {
+            compare_tick(&config.filename, &anim, tick);
+        }
+    }
+}
+
+#[test]
+fn fair() {
+    for mut config in presets::fair() {
+        config.num_ticks = NUM_TICKS;
+        let anim = fair_anim::fair_frames(&config).unwrap();
+        for tick in TICKS {
+            compare_tick(&config.filename, &anim, tick);
+        }
+    }
+}
+
+#[test]
+fn two_bottlenecks() {
+    for mut config in presets::two_bottlenecks() {
+        config.num_ticks = NUM_TICKS;
+        let anim = two_bottlenecks_anim::two_bottlenecks_frames(&config).unwrap();
+        for tick in TICKS {
+            compare_tick(&config.filename, &anim, tick);
+        }
+    }
+}
+
+#[test]
+fn elem() {
+    for mut config in presets::elem() {
+        config.num_ticks = NUM_TICKS;
+        let anim = elem_anim::elem_frames(&config).unwrap();
+        for tick in TICKS {
+            compare_tick(&config.filename, &anim, tick);
+        }
+    }
+}
+
+#[test]
+fn dumbbell() {
+    for mut config in presets::dumbbell() {
+        config.num_ticks = NUM_TICKS;
+        let anim = dumbbell_anim::dumbbell_frames(&config).unwrap();
+        for tick in TICKS {
+            compare_tick(&config.filename, &anim, tick);
+        }
+    }
+}
+
+#[test]
+fn parking_lot() {
+    for mut config in presets::parking_lot() {
+        config.num_ticks = NUM_TICKS;
+        let anim = parking_lot_anim::parking_lot_frames(&config).unwrap();
+        for tick in TICKS {
+            compare_tick(&config.filename, &anim, tick);
+        }
+    }
+}
+
+#[test]
+fn themes() {
+    for name in ["dark", "colorblind"] {
+        for mut config in presets::fair() {
+            config.num_ticks = NUM_TICKS;
+            config.output.theme = Theme::by_name(name).unwrap();
+            config.filename = config.filename.replace(".gif", &format!("-{}.gif", name));
+            let anim = fair_anim::fair_frames(&config).unwrap();
+            compare_tick(&config.filename, &anim, TICKS[1]);
+        }
+    }
+}